@@ -0,0 +1,278 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_rapier2d::prelude::{
+    Collider, CollisionGroups, QueryFilter, RapierContext, ReadRapierContext, ShapeCastOptions,
+};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    app_state::AppState,
+    loadout::Loadout,
+    paddle::{BALL_LAUNCH_OFFSET, Paddle, PaddleAction},
+    physics::{BALL_GROUP, BLOCK_GROUP, WALL_GROUP},
+};
+
+pub struct AimPlugin;
+
+impl Plugin for AimPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (update_aim, draw_trajectory)
+                .chain()
+                .run_if(in_state(AppState::Game)),
+        );
+    }
+}
+
+/// Furthest the aim can swing away from straight down, either way.
+const MAX_AIM_ANGLE: f32 = 75.0;
+const STICK_DEADZONE: f32 = 0.3;
+const PREVIEW_BOUNCES: usize = 3;
+const PREVIEW_LENGTH: f32 = 1200.0;
+
+/// Direction the paddle fires balls in.
+#[derive(Component, Debug)]
+pub struct Aim {
+    pub direction: Vec2,
+    /// Set once the player has aimed, until then (or ToggleAim) balls go out along the paddle's
+    /// tilt.
+    pub manual: bool,
+}
+
+impl Default for Aim {
+    fn default() -> Self {
+        Self {
+            direction: Vec2::NEG_Y,
+            manual: false,
+        }
+    }
+}
+
+impl Aim {
+    /// Point the aim along `direction`, kept within the allowed arc below the paddle.
+    pub fn set(&mut self, direction: Vec2) {
+        let max = MAX_AIM_ANGLE.to_radians();
+        let angle = Vec2::NEG_Y.angle_to(direction).clamp(-max, max);
+        self.direction = Vec2::NEG_Y.rotate(Vec2::from_angle(angle));
+        self.manual = true;
+    }
+
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_z(Vec2::NEG_Y.angle_to(self.direction))
+    }
+}
+
+fn update_aim(
+    mut paddle_query: Query<(&ActionState<PaddleAction>, &Transform, &mut Aim), With<Paddle>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut cursor_moved: EventReader<CursorMoved>,
+) {
+    let Ok((action_state, transform, mut aim)) = paddle_query.get_single_mut() else {
+        return;
+    };
+
+    if action_state.just_pressed(&PaddleAction::ToggleAim) {
+        // the next mouse move or stick push aims again
+        *aim = Aim::default();
+        cursor_moved.clear();
+        return;
+    }
+
+    let stick = action_state.axis_pair(&PaddleAction::Aim);
+    if stick.length() > STICK_DEADZONE {
+        aim.set(stick);
+        return;
+    }
+
+    // only follow the mouse when it moves, so it doesn't fight the stick
+    if cursor_moved.read().last().is_none() {
+        return;
+    }
+    let Some(cursor) = window_query
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+    else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+    if let Ok(target) = camera.viewport_to_world_2d(camera_transform, cursor) {
+        let direction = target - transform.translation.truncate();
+        if direction != Vec2::ZERO {
+            aim.set(direction);
+        }
+    }
+}
+
+/// Where a ball fired from `start` goes, as the points it bounces at and where the preview
+/// runs out if it doesn't end on a bounce.
+#[derive(Debug, Default)]
+pub struct Trajectory {
+    pub start: Vec2,
+    pub bounces: Vec<Vec2>,
+    pub end: Option<Vec2>,
+}
+
+/// Shape-cast a ball of `radius` from `start` along `direction` through its first few bounces.
+pub fn trajectory(
+    context: &RapierContext,
+    start: Vec2,
+    direction: Vec2,
+    radius: f32,
+) -> Trajectory {
+    let shape = Collider::ball(radius);
+    let filter = QueryFilter::new()
+        .exclude_sensors()
+        .groups(CollisionGroups::new(BALL_GROUP, WALL_GROUP | BLOCK_GROUP));
+
+    let mut trajectory = Trajectory { start, ..default() };
+    let mut position = start;
+    let mut direction = direction;
+    let mut remaining = PREVIEW_LENGTH;
+    for _ in 0..=PREVIEW_BOUNCES {
+        let hit = context.cast_shape(
+            position,
+            0.0,
+            direction,
+            &shape,
+            ShapeCastOptions::with_max_time_of_impact(remaining),
+            filter,
+        );
+        let Some((_, hit)) = hit else {
+            trajectory.end = Some(position + direction * remaining);
+            break;
+        };
+
+        let contact = position + direction * hit.time_of_impact;
+        trajectory.bounces.push(contact);
+        let Some(details) = hit.details else {
+            break;
+        };
+        // normal1 is on the collider that was hit, pointing out of it
+        let normal = details.normal1;
+        direction = direction.reflect(normal);
+        position = contact + normal * 0.5;
+        remaining -= hit.time_of_impact;
+        if remaining <= 0.0 {
+            break;
+        }
+    }
+    trajectory
+}
+
+/// Draw the next ball's path along the aim.
+fn draw_trajectory(
+    paddle_query: Query<(&Transform, &Aim), With<Paddle>>,
+    rapier_context: ReadRapierContext,
+    loadout: Res<Loadout>,
+    mut gizmos: Gizmos,
+) {
+    let Ok((transform, aim)) = paddle_query.get_single() else {
+        return;
+    };
+    if !aim.manual {
+        return;
+    }
+
+    let radius = loadout.selected().radius();
+    let colour = loadout.selected().colour().with_alpha(0.6);
+    let start = transform.translation.truncate() - Vec2::new(0.0, BALL_LAUNCH_OFFSET + radius);
+    let trajectory = trajectory(&rapier_context.single(), start, aim.direction, radius);
+
+    let mut position = trajectory.start;
+    for &contact in &trajectory.bounces {
+        gizmos.line_2d(position, contact, colour);
+        gizmos.circle_2d(contact, radius, colour);
+        position = contact;
+    }
+    if let Some(end) = trajectory.end {
+        gizmos.line_2d(position, end, colour);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy_rapier2d::prelude::ReadRapierContext;
+
+    use crate::harness::Harness;
+
+    #[test]
+    fn aimed_ball_fires_along_the_aim() {
+        let mut harness = Harness::new();
+        let paddle = harness.paddle();
+        let direction = Vec2::new(1.0, -1.0).normalize();
+        harness
+            .world_mut()
+            .get_mut::<Aim>(paddle)
+            .unwrap()
+            .set(direction);
+
+        harness.fire();
+        let ball = harness.balls()[0];
+        let velocity = harness.ball_velocity(ball).normalize();
+        assert!(velocity.angle_to(direction).abs() < 0.01, "{}", velocity);
+    }
+
+    #[test]
+    fn toggle_aim_goes_back_to_firing_along_the_tilt() {
+        let mut harness = Harness::new();
+        let paddle = harness.paddle();
+        harness
+            .world_mut()
+            .get_mut::<Aim>(paddle)
+            .unwrap()
+            .set(Vec2::new(1.0, -1.0));
+
+        harness.tap(KeyCode::KeyV);
+        let aim = harness.world().get::<Aim>(paddle).unwrap();
+        assert!(!aim.manual);
+        assert_eq!(aim.direction, Vec2::NEG_Y);
+    }
+
+    #[test]
+    fn aim_preview_follows_the_ball_through_several_bounces() {
+        let mut harness = Harness::new();
+        // a floor and a wall to the right of it
+        for (position, half_size) in [
+            (Vec2::new(0.0, -200.0), Vec2::new(1000.0, 10.0)),
+            (Vec2::new(500.0, 0.0), Vec2::new(10.0, 1000.0)),
+        ] {
+            harness.world_mut().spawn((
+                Transform::from_translation(position.extend(0.0)),
+                Collider::cuboid(half_size.x, half_size.y),
+                CollisionGroups::new(WALL_GROUP, BALL_GROUP),
+            ));
+        }
+        harness.step(1);
+
+        let path = harness
+            .world_mut()
+            .run_system_once(|context: ReadRapierContext| {
+                trajectory(
+                    &context.single(),
+                    Vec2::ZERO,
+                    Vec2::new(1.0, -1.0).normalize(),
+                    10.0,
+                )
+            })
+            .unwrap();
+
+        assert!(path.bounces.len() >= 2, "{:?}", path);
+        assert!(
+            path.bounces[0].distance(Vec2::new(180.0, -180.0)) < 1.0,
+            "{:?}",
+            path
+        );
+        assert!(
+            path.bounces[1].distance(Vec2::new(480.0, 120.0)) < 1.0,
+            "{:?}",
+            path
+        );
+    }
+}
@@ -1,38 +1,59 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use bevy_asset_loader::{
     asset_collection::AssetCollection,
     loading_state::{LoadingState, LoadingStateAppExt, config::ConfigureLoadingState},
+    mapped::AssetFileStem,
     standard_dynamic_asset::StandardDynamicAssetCollection,
 };
-use bevy_hui::prelude::{HtmlNode, HtmlTemplate};
+use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_hui::prelude::HtmlTemplate;
 
-use crate::app_state::AppState;
+use crate::{
+    app_state::AppState,
+    blocks::{BlockDefinitions, BlockRegistry},
+    refinery::{RefineryDefinitions, RefineryRecipes},
+    rooms::RoomTemplate,
+    shop::{ShopStats, UpgradeDefinitions},
+};
 
 pub struct AssetLoadingPlugin;
 
 impl Plugin for AssetLoadingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_loading_state(
-            LoadingState::new(AppState::LoadingAssets)
-                .with_dynamic_assets_file::<StandardDynamicAssetCollection>("game.assets.ron")
-                .load_collection::<GameImageAssets>()
-                .load_collection::<AudioAssets>()
-                // .load_collection::<UiComponentAssets>()
-                .continue_to_state(AppState::Game)
-                .on_failure_continue_to_state(AppState::BadStateSadEmoji),
-        );
+        app.add_plugins(RonAssetPlugin::<BlockDefinitions>::new(&["blocks.ron"]))
+            .add_plugins(RonAssetPlugin::<UpgradeDefinitions>::new(&["upgrades.ron"]))
+            .add_plugins(RonAssetPlugin::<RefineryDefinitions>::new(&[
+                "refinery.ron",
+            ]))
+            .add_plugins(RonAssetPlugin::<RoomTemplate>::new(&["room.ron"]))
+            .add_loading_state(
+                LoadingState::new(AppState::LoadingAssets)
+                    .with_dynamic_assets_file::<StandardDynamicAssetCollection>("game.assets.ron")
+                    .load_collection::<GameImageAssets>()
+                    .load_collection::<AudioAssets>()
+                    .load_collection::<BlockAssets>()
+                    .load_collection::<UpgradeAssets>()
+                    .load_collection::<RefineryAssets>()
+                    .load_collection::<UiComponentAssets>()
+                    .init_resource::<BlockRegistry>()
+                    .init_resource::<ShopStats>()
+                    .init_resource::<RefineryRecipes>()
+                    .continue_to_state(AppState::Game)
+                    .on_failure_continue_to_state(AppState::BadStateSadEmoji),
+            );
     }
 }
 
-// #[derive(AssetCollection, Resource)]
-// pub struct UiComponentAssets {
-//     #[asset(path = "ui/shop.html")]
-//     pub shop: Handle<HtmlTemplate>,
-//     #[asset(path = "ui/components/shop_item.html")]
-//     pub shop_item: Handle<HtmlTemplate>,
-//     #[asset(path = "ui/components/resource.html")]
-//     pub resource: Handle<HtmlTemplate>,
-// }
+/// Templates for the shop menu, built with bevy_hui.
+#[derive(AssetCollection, Resource)]
+pub struct UiComponentAssets {
+    #[asset(path = "ui/shop.html")]
+    pub shop: Handle<HtmlTemplate>,
+    #[asset(path = "ui/components/shop_item.html")]
+    pub shop_item: Handle<HtmlTemplate>,
+    #[asset(path = "ui/components/resource.html")]
+    pub resource: Handle<HtmlTemplate>,
+}
 
 #[derive(AssetCollection, Resource)]
 pub struct AudioAssets {
@@ -43,29 +64,41 @@ pub struct AudioAssets {
 }
 
 #[derive(AssetCollection, Resource)]
+pub struct UpgradeAssets {
+    #[asset(path = "upgrades.ron")]
+    pub definitions: Handle<UpgradeDefinitions>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct RefineryAssets {
+    #[asset(path = "refinery.ron")]
+    pub recipes: Handle<RefineryDefinitions>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct BlockAssets {
+    #[asset(path = "blocks.ron")]
+    pub definitions: Handle<BlockDefinitions>,
+    #[asset(key = "rooms", collection(typed))]
+    pub rooms: Vec<Handle<RoomTemplate>>,
+}
+
+#[derive(AssetCollection, Resource, Default)]
 pub struct GameImageAssets {
-    #[asset(key = "background")]
-    pub background: Handle<Image>,
-
-    #[asset(key = "crack")]
-    pub crack: Handle<Image>,
-
-    #[asset(key = "blue")]
-    pub blue: Handle<Image>,
-    #[asset(key = "dark_blue")]
-    pub dark_blue: Handle<Image>,
-    #[asset(key = "light_blue_transparent")]
-    pub light_blue: Handle<Image>,
-    #[asset(key = "purple")]
-    pub purple: Handle<Image>,
-    #[asset(key = "light_purple")]
-    pub light_purple: Handle<Image>,
-    #[asset(key = "pink")]
-    pub pink: Handle<Image>,
-    #[asset(key = "red")]
-    pub red: Handle<Image>,
-    #[asset(key = "orange")]
-    pub orange: Handle<Image>,
+    /// Backgrounds by file stem, picked per stratum.
+    #[asset(key = "backgrounds", collection(typed, mapped))]
+    pub backgrounds: HashMap<AssetFileStem, Handle<Image>>,
+
+    /// Crack overlays, from lightly to heavily damaged.
+    #[asset(key = "cracks", collection(typed))]
+    pub cracks: Vec<Handle<Image>>,
+
+    #[asset(key = "block_textures", collection(typed, mapped))]
+    pub block_textures: HashMap<AssetFileStem, Handle<Image>>,
+
+    /// Ball kind icons by file stem.
+    #[asset(key = "ball_icons", collection(typed, mapped))]
+    pub ball_icons: HashMap<AssetFileStem, Handle<Image>>,
 
     #[asset(key = "ufo_top")]
     pub ufo_top: Handle<Image>,
@@ -86,8 +119,24 @@ pub struct GameImageAssets {
     pub ball_capacity_icon: Handle<Image>,
     #[asset(key = "paddle_size_icon")]
     pub paddle_size_icon: Handle<Image>,
+    #[asset(key = "pierce_icon")]
+    pub pierce_icon: Handle<Image>,
+    #[asset(key = "rotate_icon")]
+    pub rotate_icon: Handle<Image>,
+    #[asset(key = "dash_icon")]
+    pub dash_icon: Handle<Image>,
+    #[asset(key = "magnet_icon")]
+    pub magnet_icon: Handle<Image>,
+    #[asset(key = "charge_icon")]
+    pub charge_icon: Handle<Image>,
+    #[asset(key = "burst_icon")]
+    pub burst_icon: Handle<Image>,
     #[asset(key = "refresh_icon")]
     pub refresh_icon: Handle<Image>,
+    #[asset(key = "respec_icon")]
+    pub respec_icon: Handle<Image>,
+    #[asset(key = "new_ball_icon")]
+    pub new_ball_icon: Handle<Image>,
 
     #[asset(key = "title")]
     pub title: Handle<Image>,
@@ -3,7 +3,12 @@ use std::time::Duration;
 use bevy::prelude::*;
 use bevy_kira_audio::{Audio, AudioControl, AudioTween};
 
-use crate::{app_state::AppState, asset_loading::AudioAssets, blocks::DespawnHack};
+use crate::{
+    app_state::AppState,
+    asset_loading::AudioAssets,
+    ball::{BallFiredEvent, BallLostEvent, BallReturnedEvent},
+    blocks::DespawnHack,
+};
 
 pub struct InternalAudioPlugin;
 
@@ -11,6 +16,9 @@ impl Plugin for InternalAudioPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(bevy_kira_audio::AudioPlugin)
             .add_observer(play_pop)
+            .add_observer(play_ball_fired)
+            .add_observer(play_ball_returned)
+            .add_observer(play_ball_lost)
             .add_systems(OnEnter(AppState::Game), play_music);
     }
 }
@@ -25,3 +33,23 @@ fn play_music(assets: Res<AudioAssets>, audio: Res<Audio>) {
 fn play_pop(trigger: Trigger<OnAdd, DespawnHack>, assets: Res<AudioAssets>, audio: Res<Audio>) {
     audio.play(assets.pop.clone());
 }
+
+// there's only the one sound effect so far, pitch it to tell ball events apart
+fn play_ball_fired(_trigger: Trigger<BallFiredEvent>, assets: Res<AudioAssets>, audio: Res<Audio>) {
+    audio.play(assets.pop.clone()).with_playback_rate(1.5);
+}
+
+fn play_ball_returned(
+    _trigger: Trigger<BallReturnedEvent>,
+    assets: Res<AudioAssets>,
+    audio: Res<Audio>,
+) {
+    audio
+        .play(assets.pop.clone())
+        .with_playback_rate(1.2)
+        .with_volume(0.6);
+}
+
+fn play_ball_lost(_trigger: Trigger<BallLostEvent>, assets: Res<AudioAssets>, audio: Res<Audio>) {
+    audio.play(assets.pop.clone()).with_playback_rate(0.5);
+}
@@ -10,27 +10,183 @@ use std::time::Duration;
 use crate::{
     app_state::AppState,
     asset_loading::GameImageAssets,
-    blocks::BlockType,
+    behaviours::SlowBallEvent,
+    blocks::{BlockRegistry, BlockType},
+    loadout::BallKind,
     particles::BoxParticlesEvent,
-    physics::{BALL_GROUP, BLOCK_GROUP, PADDLE_GROUP, WALL_GROUP},
+    physics::{BALL_GROUP, BLOCK_GROUP, PADDLE_GROUP, WALL_GROUP, process_collisions},
+    shop::ShopStats,
 };
 
 pub struct BallPlugin;
 
 impl Plugin for BallPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            // TODO verify if we need to do any ordering here..
-            FixedUpdate,
-            override_physics.run_if(in_state(AppState::Game)),
-        )
-        .add_systems(Update, spawn_trail.run_if(in_state(AppState::Game)));
+        app.init_resource::<BallSpeedRules>()
+            .init_resource::<BallLossRules>()
+            .add_systems(
+                FixedUpdate,
+                check_lost_balls.run_if(in_state(AppState::Game)),
+            )
+            // rapier steps in PostUpdate, so in FixedUpdate a bounce that lost speed would show
+            // for a frame; run straight after the step and the bounce events it caused instead
+            .add_systems(
+                PostUpdate,
+                override_physics
+                    .after(process_collisions)
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(Update, spawn_trail.run_if(in_state(AppState::Game)))
+            .add_observer(slow_ball)
+            .add_observer(pierce_block)
+            .add_observer(speed_up_ball);
     }
 }
 
 #[derive(Component)]
 pub struct Ball;
 
+/// How ball speed changes as it bounces around, applied on top of rapier's bounce.
+#[derive(Resource, Debug)]
+pub struct BallSpeedRules {
+    /// Speed multiplier for each block hit.
+    pub block_hit: f32,
+    /// Speed multiplier for each paddle hit.
+    pub paddle_hit: f32,
+    /// Speed multiplier for each wall hit.
+    pub wall_hit: f32,
+    /// Slowest a ball can go, before upgrades.
+    pub min_speed: f32,
+    /// Fastest a ball can go, before upgrades.
+    pub max_speed: f32,
+    /// Stops balls getting stuck bouncing sideways between the walls.
+    pub min_vertical_speed: f32,
+}
+
+impl Default for BallSpeedRules {
+    fn default() -> Self {
+        Self {
+            block_hit: 1.05,
+            paddle_hit: 1.5,
+            wall_hit: 1.05,
+            min_speed: 100.0,
+            max_speed: 500.0,
+            min_vertical_speed: 40.0,
+        }
+    }
+}
+
+impl BallSpeedRules {
+    pub fn multiplier(&self, surface: BounceSurface) -> f32 {
+        match surface {
+            BounceSurface::Block => self.block_hit,
+            BounceSurface::Paddle => self.paddle_hit,
+            BounceSurface::Wall => self.wall_hit,
+        }
+    }
+
+    /// Keep a velocity within the ball's speed limits and moving up or down.
+    pub fn limit(&self, linvel: Vec2, stats: &BallStats) -> Vec2 {
+        let linvel = linvel.clamp_length(stats.min_speed, stats.max_speed);
+        if linvel.y.abs() >= self.min_vertical_speed {
+            return linvel;
+        }
+        let speed = linvel.length();
+        let y = if linvel.y > 0.0 {
+            self.min_vertical_speed
+        } else {
+            -self.min_vertical_speed
+        };
+        let x = (speed * speed - y * y).max(0.0).sqrt();
+        Vec2::new(if linvel.x < 0.0 { -x } else { x }, y)
+    }
+}
+
+/// Stats of a single ball, worked out from the upgrades and ball kind when it's fired.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BallStats {
+    pub damage: u16,
+    pub max_speed: f32,
+    pub min_speed: f32,
+    /// Blocks the ball can still break through before it bounces.
+    pub pierce: u32,
+    /// Seconds before the ball is removed, `None` keeps it until it's lost some other way.
+    pub lifetime: Option<f32>,
+}
+
+impl BallStats {
+    pub fn new(
+        shop_stats: &ShopStats,
+        speed_rules: &BallSpeedRules,
+        loss_rules: &BallLossRules,
+        kind: BallKind,
+    ) -> Self {
+        Self {
+            damage: shop_stats.damage() * kind.damage_multiplier(),
+            max_speed: speed_rules.max_speed * kind.speed_multiplier(),
+            min_speed: speed_rules.min_speed * kind.speed_multiplier(),
+            pierce: shop_stats.pierce(),
+            lifetime: loss_rules.lifetime,
+        }
+    }
+}
+
+/// When a ball counts as lost.
+#[derive(Resource, Debug)]
+pub struct BallLossRules {
+    /// Seconds a ball lives for, before upgrades. Unlimited by default.
+    pub lifetime: Option<f32>,
+    /// Balls above this have left the world.
+    pub max_height: f32,
+    /// Seconds a ball can stay within `stuck_distance` of one spot.
+    pub stuck_time: f32,
+    pub stuck_distance: f32,
+}
+
+impl Default for BallLossRules {
+    fn default() -> Self {
+        Self {
+            lifetime: None,
+            max_height: 3000.0,
+            stuck_time: 5.0,
+            stuck_distance: 30.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct BallLifetime(Timer);
+
+/// Where the ball was last seen making progress.
+#[derive(Component)]
+pub struct StuckCheck {
+    anchor: Vec2,
+    seconds: f32,
+}
+
+/// Triggered on every ball as it is spawned.
+#[derive(Event, Debug)]
+pub struct BallFiredEvent;
+
+/// Triggered on a ball when it hands its resources over to the paddle.
+#[derive(Event, Debug)]
+pub struct BallReturnedEvent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BallLostReason {
+    OutOfBounds,
+    Expired,
+    Stuck,
+}
+
+/// Fired just before a ball is despawned, with whatever it was still carrying.
+#[derive(Event, Debug)]
+pub struct BallLostEvent {
+    pub position: Vec2,
+    pub reason: BallLostReason,
+    pub resources: HashMap<BlockType, u32>,
+}
+
 #[derive(Component)]
 #[require(Velocity)]
 pub struct PreviousVelocity {
@@ -56,7 +212,11 @@ impl CollectedResources {
     }
 
     pub fn add(&mut self, block_type: BlockType) {
-        *self.counts.entry(block_type).or_insert(0) += 1;
+        self.add_many(block_type, 1);
+    }
+
+    pub fn add_many(&mut self, block_type: BlockType, count: u32) {
+        *self.counts.entry(block_type).or_insert(0) += count;
     }
 
     pub fn clear(&mut self) {
@@ -70,19 +230,37 @@ impl CollectedResources {
     }
 }
 
-pub fn spawn_ball(commands: &mut Commands, transform: Transform, assets: Res<GameImageAssets>) {
-    let mut rng = rand::rng();
-    commands.spawn((
-        Ball,
+pub fn spawn_ball(
+    commands: &mut Commands,
+    transform: Transform,
+    assets: &GameImageAssets,
+    kind: BallKind,
+    stats: BallStats,
+) -> Entity {
+    let radius = kind.radius();
+    let mut ball = commands.spawn((
+        (
+            Ball,
+            kind,
+            stats,
+            StuckCheck {
+                anchor: transform.translation.truncate(),
+                seconds: 0.0,
+            },
+        ),
         // Sprite::from_color(Color::srgb(0.5, 0.5 as f32, 0.5), Vec2 { x: 10.0, y: 10.0 }),
         Sprite {
             image: assets.ball.clone(),
-            custom_size: Some(Vec2::new(10.0, 10.0)),
-            // color: Color::srgb(1.1, 1.1, 1.1),
+            custom_size: Some(Vec2::splat(radius * 2.0)),
+            color: kind.colour(),
             ..Default::default()
         },
-        Transform::from_xyz(transform.translation.x, transform.translation.y - 5.0, 0.0),
-        Collider::ball(5.0),
+        Transform::from_xyz(
+            transform.translation.x,
+            transform.translation.y - radius,
+            0.0,
+        ),
+        Collider::ball(radius),
         RigidBody::Dynamic,
         GravityScale(0.0),
         Friction::coefficient(0.0),
@@ -114,60 +292,37 @@ pub fn spawn_ball(commands: &mut Commands, transform: Transform, assets: Res<Gam
         Velocity::linear(
             transform
                 .rotation
-                .mul_vec3(Vec3::new(0.0, -100.0, 0.0))
-                .truncate()
-                .rotate(Vec2::from_angle(
-                    rng.random_range(-5.0_f32.to_radians()..5.0_f32.to_radians()),
-                )),
+                .mul_vec3(Vec3::new(0.0, -stats.min_speed, 0.0))
+                .truncate(),
         ),
         PreviousVelocity::zero(),
         CollectedResources::new(),
     ));
+    if let Some(lifetime) = stats.lifetime {
+        ball.insert(BallLifetime(Timer::from_seconds(lifetime, TimerMode::Once)));
+    }
+    let ball = ball.id();
+    commands.trigger_targets(BallFiredEvent, ball);
+    ball
 }
 
 fn override_physics(
-    mut query: Query<
-        (
-            Entity,
-            &Transform,
-            &mut Velocity,
-            &mut PreviousVelocity,
-            &CollectedResources,
-        ),
-        With<Ball>,
-    >,
-    mut commands: Commands,
+    mut query: Query<(&mut Velocity, &mut PreviousVelocity, &BallStats), With<Ball>>,
+    rules: Res<BallSpeedRules>,
 ) {
-    for (entity, transform, mut velocity, mut previous_velocity, collected_resources) in
-        query.iter_mut()
-    {
-        // Check if the ball is outside the screen bounds
-        if transform.translation.y > 3000.0 {
-            commands.entity(entity).despawn_recursive();
-            continue;
-        }
-
+    for (mut velocity, mut previous_velocity, stats) in query.iter_mut() {
         // Check if velocity has changed (can't use Changed<Velocity> since rapier updates it every time)
         if velocity.linvel == previous_velocity.linvel {
             continue;
         }
 
-        // println!("Collected resources: {:?}", collected_resources);
-
         // Velocity has changed (collision) so lets check if we need to modify it
         let mut new_vel = velocity.linvel;
         if new_vel.length_squared() < previous_velocity.linvel.length_squared() {
             // balls are not allowed to slow down
             new_vel = new_vel.normalize() * previous_velocity.linvel.length();
         }
-        // TODO handle speed increasing here instead of using Restitution
-
-        const MAX_VELOCITY: f32 = 500.0;
-        if new_vel.length_squared() > MAX_VELOCITY * MAX_VELOCITY {
-            // clamp the speed
-            // TODO move this number to a component
-            new_vel = new_vel.normalize() * MAX_VELOCITY;
-        }
+        new_vel = rules.limit(new_vel, stats);
 
         if new_vel != velocity.linvel {
             velocity.linvel = new_vel;
@@ -176,9 +331,110 @@ fn override_physics(
     }
 }
 
+type LostBallQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        Option<&'static mut BallLifetime>,
+        &'static mut StuckCheck,
+        &'static CollectedResources,
+    ),
+    With<Ball>,
+>;
+
+fn check_lost_balls(
+    mut query: LostBallQuery,
+    rules: Res<BallLossRules>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, transform, lifetime, mut stuck, collected_resources) in query.iter_mut() {
+        let position = transform.translation.truncate();
+        if position.distance(stuck.anchor) > rules.stuck_distance {
+            stuck.anchor = position;
+            stuck.seconds = 0.0;
+        } else {
+            stuck.seconds += time.delta_secs();
+        }
+
+        let reason = if position.y > rules.max_height {
+            BallLostReason::OutOfBounds
+        } else if lifetime.is_some_and(|mut lifetime| lifetime.0.tick(time.delta()).finished()) {
+            BallLostReason::Expired
+        } else if stuck.seconds > rules.stuck_time {
+            BallLostReason::Stuck
+        } else {
+            continue;
+        };
+
+        debug!("Lost ball {} ({:?})", entity, reason);
+        commands.trigger(BallLostEvent {
+            position,
+            reason,
+            resources: collected_resources.counts.clone(),
+        });
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Triggered on a ball that broke through a block instead of bouncing off it.
+#[derive(Event, Debug)]
+pub struct PierceBlockEvent;
+
+fn pierce_block(
+    trigger: Trigger<PierceBlockEvent>,
+    mut query: Query<(&mut Velocity, &PreviousVelocity), With<Ball>>,
+) {
+    if let Ok((mut velocity, previous_velocity)) = query.get_mut(trigger.entity()) {
+        // carry on as if the block was never there
+        velocity.linvel = previous_velocity.linvel;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BounceSurface {
+    Block,
+    Paddle,
+    Wall,
+}
+
+/// Triggered on a ball when it bounces off something, after rapier has worked out the bounce.
+#[derive(Event, Debug)]
+pub struct BallBounceEvent {
+    pub surface: BounceSurface,
+}
+
+fn speed_up_ball(
+    trigger: Trigger<BallBounceEvent>,
+    mut query: Query<(&mut Velocity, &mut PreviousVelocity, &BallStats), With<Ball>>,
+    rules: Res<BallSpeedRules>,
+) {
+    if let Ok((mut velocity, mut previous_velocity, stats)) = query.get_mut(trigger.entity()) {
+        // on top of any slow from the block, so ice still takes its share off
+        let linvel = velocity.linvel * rules.multiplier(trigger.surface);
+        velocity.linvel = rules.limit(linvel, stats);
+        // override_physics has nothing left to correct
+        previous_velocity.linvel = velocity.linvel;
+    }
+}
+
+fn slow_ball(
+    trigger: Trigger<SlowBallEvent>,
+    mut query: Query<(&mut Velocity, &mut PreviousVelocity), With<Ball>>,
+) {
+    if let Ok((mut velocity, mut previous_velocity)) = query.get_mut(trigger.entity()) {
+        // update both so override_physics doesn't treat this as a slow down to undo
+        velocity.linvel *= trigger.speed_factor;
+        previous_velocity.linvel = velocity.linvel;
+    }
+}
+
 fn spawn_trail(
     mut commands: Commands,
     ball_query: Query<(Entity, &Transform, &Velocity, &CollectedResources), With<Ball>>,
+    registry: Res<BlockRegistry>,
 ) {
     let mut rng = rand::rng();
     for (entity, transform, velocity, collected_resources) in ball_query.iter() {
@@ -197,12 +453,9 @@ fn spawn_trail(
                 _ => num_spawns = 3,
             }
 
-            let bloom_color = Color::srgba(
-                block_type.colour().to_srgba().red * 1.1,
-                block_type.colour().to_srgba().green * 1.1,
-                block_type.colour().to_srgba().blue * 1.1,
-                1.0,
-            );
+            let colour = registry.get(*block_type).colour.to_srgba();
+            let bloom_color =
+                Color::srgba(colour.red * 1.1, colour.green * 1.1, colour.blue * 1.1, 1.0);
 
             for _ in 0..num_spawns {
                 commands.trigger(BoxParticlesEvent {
@@ -223,3 +476,124 @@ fn spawn_trail(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::{Harness, cell_centre};
+
+    #[test]
+    fn sideways_ball_is_kept_moving_vertically() {
+        let mut harness = Harness::new();
+        harness.fire();
+        let ball = harness.balls()[0];
+        harness
+            .world_mut()
+            .get_mut::<Velocity>(ball)
+            .unwrap()
+            .linvel = Vec2::new(300.0, 0.0);
+        harness.step(1);
+
+        let min_vertical_speed = harness
+            .world()
+            .resource::<BallSpeedRules>()
+            .min_vertical_speed;
+        let velocity = harness.ball_velocity(ball);
+        assert!(
+            velocity.y.abs() >= min_vertical_speed - 0.01,
+            "{}",
+            velocity
+        );
+        assert!((velocity.length() - 300.0).abs() < 0.01, "{}", velocity);
+    }
+
+    #[test]
+    fn ice_slows_a_ball_before_the_bounce_speeds_it_up() {
+        let mut harness = Harness::new();
+        harness.spawn_block(20, 4, "ice");
+        harness.set_paddle_position(cell_centre(20, 0));
+
+        harness.fire();
+        let ball = harness.balls()[0];
+        // fast enough that the slowed bounce stays above the minimum speed
+        let initial_speed = 200.0;
+        harness
+            .world_mut()
+            .get_mut::<Velocity>(ball)
+            .unwrap()
+            .linvel = Vec2::NEG_Y * initial_speed;
+        harness
+            .world_mut()
+            .get_mut::<PreviousVelocity>(ball)
+            .unwrap()
+            .linvel = Vec2::NEG_Y * initial_speed;
+        for _ in 0..120 {
+            harness.step(1);
+            if harness.ball_velocity(ball).y > 0.0 {
+                break;
+            }
+        }
+        let rules = harness.world().resource::<BallSpeedRules>();
+        let expected = initial_speed * 0.8 * rules.block_hit;
+        let speed = harness.ball_velocity(ball).length();
+        assert!((speed - expected).abs() < 1.0, "{} != {}", speed, expected);
+    }
+
+    #[test]
+    fn ball_never_slows_down() {
+        let mut harness = Harness::new();
+        harness.spawn_block(20, 4, "blue");
+        harness.set_paddle_position(cell_centre(20, 0));
+
+        harness.fire();
+        let ball = harness.balls()[0];
+        let initial_speed = harness.ball_velocity(ball).length();
+        for _ in 0..120 {
+            harness.step(1);
+            let speed = harness.ball_velocity(ball).length();
+            assert!(
+                speed >= initial_speed - 0.01,
+                "{} < {}",
+                speed,
+                initial_speed
+            );
+        }
+    }
+
+    #[test]
+    fn bounce_slow_down_is_undone_in_the_same_frame() {
+        let mut harness = Harness::new();
+        harness.spawn_block(20, 4, "blue");
+        harness.set_paddle_position(cell_centre(20, 0));
+
+        harness.fire();
+        let ball = harness.balls()[0];
+        // no jitter, so it comes back to the paddle the same way every run
+        let linvel = Vec2::new(8.0, -100.0);
+        harness
+            .world_mut()
+            .get_mut::<Velocity>(ball)
+            .unwrap()
+            .linvel = linvel;
+        harness
+            .world_mut()
+            .get_mut::<PreviousVelocity>(ball)
+            .unwrap()
+            .linvel = linvel;
+        let mut last_speed = linvel.length();
+        for step in 0..180 {
+            harness.step(1);
+            // a bounce can only speed the ball up, never show it slower for a frame
+            let speed = harness.ball_velocity(ball).length();
+            assert!(
+                speed >= last_speed - 0.01,
+                "step {}: {} < {}",
+                step,
+                speed,
+                last_speed
+            );
+            last_speed = speed;
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    ball::{Ball, CollectedResources},
+    blocks::{Block, BlockGrid, BlockPosition, BlockRegistry, DespawnHack, HitPoints, block_break},
+};
+
+pub struct BlockBehavioursPlugin;
+
+impl Plugin for BlockBehavioursPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(on_block_explosion)
+            .add_observer(on_block_damage)
+            .add_systems(
+                FixedUpdate,
+                regenerate_blocks.run_if(in_state(AppState::Game)),
+            );
+    }
+}
+
+/// Special behaviour of a block type, declared per block in `blocks.ron`.
+#[derive(Component, Deserialize, Debug, Clone, Copy)]
+pub enum BlockBehaviour {
+    /// Damages every block within `radius` cells when broken.
+    Explosive { radius: usize, damage: u16 },
+    /// Never breaks, balls just bounce off.
+    Unbreakable,
+    /// Scales the speed of balls that hit it.
+    Slippery { speed_factor: f32 },
+    /// Heals `amount` hitpoints every `interval` seconds.
+    Regenerating { interval: f32, amount: u16 },
+}
+
+impl BlockBehaviour {
+    pub fn insert(&self, entity_commands: &mut EntityCommands) {
+        entity_commands.try_insert(*self);
+        if let BlockBehaviour::Regenerating { interval, .. } = self {
+            // blocks restored from a chunk bring their own timer
+            entity_commands.try_insert_if_new(RegenerationTimer(Timer::new(
+                Duration::from_secs_f32(*interval),
+                TimerMode::Repeating,
+            )));
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct RegenerationTimer(pub Timer);
+
+#[derive(Event, Debug)]
+pub struct BlockExplosionEvent {
+    pub position: BlockPosition,
+    pub radius: usize,
+    pub damage: u16,
+    /// Ball that set off the explosion, it collects whatever breaks.
+    pub ball: Entity,
+}
+
+/// Damage specific blocks, e.g. from a lightning arc.
+#[derive(Event, Debug)]
+pub struct BlockDamageEvent {
+    pub blocks: Vec<Entity>,
+    pub damage: u16,
+    /// Ball that caused the damage, it collects whatever breaks.
+    pub ball: Entity,
+}
+
+#[derive(Event, Debug)]
+pub struct SlowBallEvent {
+    pub speed_factor: f32,
+}
+
+type DamageableBlockQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Block,
+        &'static BlockPosition,
+        &'static mut HitPoints,
+        &'static Transform,
+        Option<&'static BlockBehaviour>,
+    ),
+    Without<DespawnHack>,
+>;
+
+fn on_block_explosion(
+    trigger: Trigger<BlockExplosionEvent>,
+    grid: Res<BlockGrid>,
+    mut block_query: DamageableBlockQuery,
+    mut ball_query: Query<&mut CollectedResources, With<Ball>>,
+    registry: Res<BlockRegistry>,
+    mut commands: Commands,
+) {
+    let explosion = trigger.event();
+    let blocks = grid
+        .around(
+            explosion.position.column,
+            explosion.position.row,
+            explosion.radius,
+        )
+        .map(|(_, cell)| cell.entity);
+    damage_blocks(
+        blocks,
+        explosion.damage,
+        explosion.ball,
+        &mut block_query,
+        &mut ball_query,
+        &registry,
+        &mut commands,
+    );
+}
+
+fn on_block_damage(
+    trigger: Trigger<BlockDamageEvent>,
+    mut block_query: DamageableBlockQuery,
+    mut ball_query: Query<&mut CollectedResources, With<Ball>>,
+    registry: Res<BlockRegistry>,
+    mut commands: Commands,
+) {
+    let event = trigger.event();
+    damage_blocks(
+        event.blocks.iter().copied(),
+        event.damage,
+        event.ball,
+        &mut block_query,
+        &mut ball_query,
+        &registry,
+        &mut commands,
+    );
+}
+
+fn damage_blocks(
+    blocks: impl Iterator<Item = Entity>,
+    damage: u16,
+    ball: Entity,
+    block_query: &mut DamageableBlockQuery,
+    ball_query: &mut Query<&mut CollectedResources, With<Ball>>,
+    registry: &BlockRegistry,
+    commands: &mut Commands,
+) {
+    let mut ball_resources = ball_query.get_mut(ball).ok();
+    for entity in blocks {
+        let Ok((block, position, mut hitpoints, transform, behaviour)) =
+            block_query.get_mut(entity)
+        else {
+            continue;
+        };
+        if let Some(BlockBehaviour::Unbreakable) = behaviour {
+            continue;
+        }
+        if hitpoints.damage(damage).is_ok() {
+            continue;
+        }
+
+        commands.entity(entity).insert(DespawnHack);
+        block_break(block.0, transform, commands, registry);
+        if let Some(resources) = ball_resources.as_mut() {
+            resources.add(block.0);
+        }
+        // chain reaction, runs once this explosion's despawns are applied
+        if let Some(BlockBehaviour::Explosive { radius, damage }) = behaviour {
+            commands.trigger(BlockExplosionEvent {
+                position: *position,
+                radius: *radius,
+                damage: *damage,
+                ball,
+            });
+        }
+    }
+}
+
+fn regenerate_blocks(
+    mut query: Query<
+        (
+            &Block,
+            &mut HitPoints,
+            &mut RegenerationTimer,
+            &BlockBehaviour,
+        ),
+        Without<DespawnHack>,
+    >,
+    registry: Res<BlockRegistry>,
+    time: Res<Time>,
+) {
+    for (block, mut hitpoints, mut timer, behaviour) in query.iter_mut() {
+        if !timer.0.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let max_hitpoints = registry.get(block.0).max_hitpoints;
+        // only touch damaged blocks so the crack and grid systems don't see a change
+        if hitpoints.current() >= max_hitpoints {
+            continue;
+        }
+        if let BlockBehaviour::Regenerating { amount, .. } = behaviour {
+            hitpoints.heal(*amount, max_hitpoints);
+        }
+    }
+}
@@ -1,37 +1,54 @@
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite::future},
+};
+use bevy_asset_loader::mapped::AssetFileStem;
 use bevy_rapier2d::prelude::{Collider, CollisionGroups, Friction, Restitution, RigidBody};
-use noise::{Fbm, MultiFractal, NoiseFn, Perlin, RidgedMulti};
-use rand::Rng;
-use strum_macros::EnumIter;
+use serde::Deserialize;
 
 use crate::{
     app_state::AppState,
-    asset_loading::GameImageAssets,
+    asset_loading::{BlockAssets, GameImageAssets},
     ball::Ball,
+    behaviours::BlockBehaviour,
     particles::BoxParticlesEvent,
     physics::{BALL_GROUP, BLOCK_GROUP, PADDLE_GROUP, WALL_GROUP},
+    rooms::{RoomInfo, RoomTemplate},
+    seed::Seed,
+    strata::{StratumDefinition, StratumInfo},
+    worldgen::WorldGenerator,
 };
 
 pub struct BlocksPlugin;
 
 impl Plugin for BlocksPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Seed(rand::rng().random_range(0..17851437)))
-            .add_systems(OnEnter(AppState::Game), spawn_blocks)
+        app.init_resource::<PendingRows>()
+            .init_resource::<BlockGrid>()
+            .add_systems(
+                OnEnter(AppState::Game),
+                (init_world_generator, spawn_blocks).chain(),
+            )
             .add_systems(
                 FixedUpdate,
-                check_for_new_block_depths.run_if(in_state(AppState::Game)),
+                (check_for_new_block_depths, spawn_generated_rows)
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
             )
             .add_observer(on_add_block)
+            .add_observer(on_remove_block)
+            .add_observer(on_block_broken)
+            .add_systems(
+                Update,
+                update_crack_sprites.run_if(in_state(AppState::Game)),
+            )
+            .add_systems(Last, sync_block_grid.run_if(in_state(AppState::Game)))
             .add_systems(PreUpdate, despawn_hack.run_if(in_state(AppState::Game)));
     }
 }
 
-#[derive(Resource)]
-pub struct Seed(u32);
-
 pub const WALL_WIDTH: f32 = 10.0;
 pub const BLOCK_SIZE: f32 = 30.0;
 pub const BLOCK_COUNT_WIDTH: usize = 40;
@@ -39,11 +56,22 @@ pub const BLOCK_GAP_SIZE: f32 = 0.0;
 pub const BLOCK_GROUP_OFFSET: f32 =
     (BLOCK_SIZE * BLOCK_COUNT_WIDTH as f32 + BLOCK_GAP_SIZE * (BLOCK_COUNT_WIDTH - 1) as f32) / 2.0;
 
-fn spawn_blocks(mut commands: Commands, seed: Res<Seed>) {
+pub fn init_world_generator(
+    mut commands: Commands,
+    seed: Res<Seed>,
+    registry: Res<BlockRegistry>,
+    mut grid: ResMut<BlockGrid>,
+    mut pending_rows: ResMut<PendingRows>,
+) {
+    commands.insert_resource(WorldGenerator::new(&registry, *seed));
+    grid.clear();
+    // drops rows still generating for the last run
+    *pending_rows = PendingRows::default();
+}
+
+fn spawn_blocks(mut commands: Commands, generator: Res<WorldGenerator>) {
     for i in 0..BLOCK_COUNT_WIDTH {
-        for j in 0..BLOCK_COUNT_WIDTH {
-            spawn_block_at(j, i, &mut commands, seed.0);
-        }
+        spawn_row(i, generator.generate_row(i), &mut commands);
     }
 
     // Spawn walls/planes on the sides.
@@ -51,16 +79,18 @@ fn spawn_blocks(mut commands: Commands, seed: Res<Seed>) {
         Transform::from_xyz(BLOCK_GROUP_OFFSET, 0.0, 0.0),
         RigidBody::Fixed,
         Friction::coefficient(0.0),
-        Restitution::coefficient(1.1),
+        Restitution::coefficient(1.0),
         Collider::halfspace(Vec2 { x: -1.0, y: 0.0 }).unwrap(),
+        Wall,
         CollisionGroups::new(WALL_GROUP, BALL_GROUP | PADDLE_GROUP),
     ));
     commands.spawn((
         Transform::from_xyz(-BLOCK_GROUP_OFFSET, 0.0, 0.0),
         RigidBody::Fixed,
         Friction::coefficient(0.0),
-        Restitution::coefficient(1.1),
+        Restitution::coefficient(1.0),
         Collider::halfspace(Vec2 { x: 1.0, y: 0.0 }).unwrap(),
+        Wall,
         CollisionGroups::new(WALL_GROUP, BALL_GROUP | PADDLE_GROUP),
     ));
 
@@ -95,15 +125,26 @@ fn spawn_blocks(mut commands: Commands, seed: Res<Seed>) {
     //     });
 }
 
-fn spawn_block_at(j: usize, i: usize, commands: &mut Commands, seed: u32) {
-    let block_type = pick_block_type(
-        Vec2 {
-            x: j as f32,
-            y: i as f32,
-        },
-        seed,
-    );
-    commands.spawn((
+fn spawn_row(i: usize, row: Vec<Option<BlockType>>, commands: &mut Commands) {
+    for (j, block_type) in row.into_iter().enumerate() {
+        // empty cells are caves
+        if let Some(block_type) = block_type {
+            spawn_block_at(j, i, block_type, commands);
+        }
+    }
+}
+
+pub fn spawn_block_at(
+    j: usize,
+    i: usize,
+    block_type: BlockType,
+    commands: &mut Commands,
+) -> Entity {
+    commands.spawn(block_bundle(j, i, block_type)).id()
+}
+
+pub fn block_bundle(j: usize, i: usize, block_type: BlockType) -> impl Bundle {
+    (
         Transform::from_xyz(
             -BLOCK_GROUP_OFFSET + j as f32 * (BLOCK_SIZE + BLOCK_GAP_SIZE) + BLOCK_SIZE / 2.0,
             i as f32 * -(BLOCK_SIZE + BLOCK_GAP_SIZE) + BLOCK_SIZE / 2.0,
@@ -112,24 +153,26 @@ fn spawn_block_at(j: usize, i: usize, commands: &mut Commands, seed: u32) {
         Collider::cuboid(BLOCK_SIZE / 2.0, BLOCK_SIZE / 2.0),
         RigidBody::Fixed,
         Friction::coefficient(0.0),
-        Restitution::coefficient(1.1),
+        Restitution::coefficient(1.0),
         Block(block_type),
+        BlockPosition { column: j, row: i },
         StateScoped(AppState::Game),
         Name::new(format!("Block {} {}", i, j)),
         CollisionGroups::new(BLOCK_GROUP, BALL_GROUP | PADDLE_GROUP),
-    ));
+    )
+}
+
+/// Convert a world y position into a block row (negative above the surface).
+pub fn row_at(y: f32) -> i32 {
+    ((BLOCK_SIZE / 2.0 - y) / (BLOCK_SIZE + BLOCK_GAP_SIZE)).round() as i32
 }
 
 fn check_for_new_block_depths(
     camera_query: Query<(&Camera, &GlobalTransform)>,
     balls_query: Query<&Transform, With<Ball>>,
-    mut deepest_layer: Local<usize>,
-    mut commands: Commands,
-    seed: Res<Seed>,
+    generator: Res<WorldGenerator>,
+    mut pending_rows: ResMut<PendingRows>,
 ) {
-    if *deepest_layer == 0 {
-        *deepest_layer = BLOCK_COUNT_WIDTH;
-    }
     let (camera, camera_transform) = camera_query
         .get_single()
         .expect("Need single camera to check depth.");
@@ -137,7 +180,8 @@ fn check_for_new_block_depths(
         .viewport_to_world_2d(camera_transform, camera.logical_viewport_size().unwrap())
         .expect("Need viewport position to check depth.");
 
-    const BUFFER: usize = 2;
+    // rows are generated off the main thread, so ask for them a little earlier
+    const BUFFER: usize = 4;
     let mut current_depth =
         ((viewport_position.y - BLOCK_SIZE / 2.0) / -(BLOCK_SIZE + BLOCK_GAP_SIZE)).ceil() as usize;
 
@@ -150,44 +194,133 @@ fn check_for_new_block_depths(
     }
     current_depth += BUFFER;
 
-    if current_depth > *deepest_layer {
-        println!("Current depth: {}", current_depth);
-        for l in *deepest_layer..current_depth {
-            for j in 0..BLOCK_COUNT_WIDTH {
-                spawn_block_at(j, l, &mut commands, seed.0);
-            }
+    if current_depth > pending_rows.next_row {
+        debug!("Current depth: {}", current_depth);
+        let task_pool = AsyncComputeTaskPool::get();
+        for l in pending_rows.next_row..current_depth {
+            let generator = generator.clone();
+            pending_rows
+                .tasks
+                .push((l, task_pool.spawn(async move { generator.generate_row(l) })));
         }
-        *deepest_layer = current_depth;
+        pending_rows.next_row = current_depth;
     }
 }
 
+/// Rows that are being generated on the async compute pool.
+#[derive(Resource)]
+pub struct PendingRows {
+    tasks: Vec<(usize, Task<Vec<Option<BlockType>>>)>,
+    /// First row that hasn't been asked for yet.
+    next_row: usize,
+}
+
+impl Default for PendingRows {
+    fn default() -> Self {
+        Self {
+            tasks: Vec::new(),
+            // spawn_blocks makes the rows above this
+            next_row: BLOCK_COUNT_WIDTH,
+        }
+    }
+}
+
+fn spawn_generated_rows(mut pending_rows: ResMut<PendingRows>, mut commands: Commands) {
+    pending_rows
+        .tasks
+        .retain_mut(|(l, task)| match block_on(future::poll_once(task)) {
+            Some(row) => {
+                spawn_row(*l, row, &mut commands);
+                false
+            }
+            None => true,
+        });
+}
+
 #[derive(Component, Debug, Clone, Copy)]
 pub struct Block(pub BlockType);
 
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockPosition {
+    pub column: usize,
+    pub row: usize,
+}
+
 #[derive(Component, Debug, Clone, Copy)]
 pub struct CrackSprite;
 
-fn on_add_block(
-    trigger: Trigger<OnAdd, Block>,
-    query: Query<&Block>,
+/// Which crack texture to show for the remaining hitpoints, `None` while undamaged.
+fn crack_stage(hitpoints: u16, max_hitpoints: u16, stages: usize) -> Option<usize> {
+    if stages == 0 || hitpoints >= max_hitpoints {
+        return None;
+    }
+    let damaged = 1.0 - hitpoints as f32 / max_hitpoints as f32;
+    Some(((damaged * stages as f32).ceil() as usize).clamp(1, stages) - 1)
+}
+
+fn update_crack_sprites(
+    block_query: Query<(Entity, &Block, &HitPoints, Option<&Children>), Changed<HitPoints>>,
+    mut crack_query: Query<&mut Sprite, With<CrackSprite>>,
     mut commands: Commands,
+    registry: Res<BlockRegistry>,
     assets: Res<GameImageAssets>,
 ) {
-    if let Ok(block) = query.get(trigger.entity()) {
-        // let crack = commands
-        //     .spawn((
-        //         CrackSprite,
-        //         Sprite {
-        //             image: assets.crack.clone(),
-        //             custom_size: Some(Vec2 {
-        //                 x: BLOCK_SIZE,
-        //                 y: BLOCK_SIZE,
-        //             }),
-        //             color: Color::srgba(1.0, 1.0, 1.0, 0.0),
-        //             ..Default::default()
-        //         },
-        //     ))
-        //     .id();
+    for (entity, block, hitpoints, children) in block_query.iter() {
+        let existing = children
+            .into_iter()
+            .flatten()
+            .find(|child| crack_query.contains(**child));
+        let Some(stage) = crack_stage(
+            hitpoints.0,
+            registry.get(block.0).max_hitpoints,
+            assets.cracks.len(),
+        ) else {
+            // fully healed
+            if let Some(&crack) = existing {
+                commands.entity(crack).despawn_recursive();
+            }
+            continue;
+        };
+        let image = assets.cracks[stage].clone();
+
+        if let Some(&crack) = existing {
+            if let Ok(mut sprite) = crack_query.get_mut(crack) {
+                sprite.image = image;
+            }
+        } else {
+            commands.entity(entity).with_child((
+                CrackSprite,
+                Sprite {
+                    image,
+                    custom_size: Some(Vec2 {
+                        x: BLOCK_SIZE,
+                        y: BLOCK_SIZE,
+                    }),
+                    ..Default::default()
+                },
+                Transform::from_xyz(0.0, 0.0, 0.1),
+            ));
+        }
+    }
+}
+
+pub fn on_add_block(
+    trigger: Trigger<OnAdd, Block>,
+    query: Query<(&Block, &BlockPosition, Option<&HitPoints>)>,
+    mut commands: Commands,
+    registry: Res<BlockRegistry>,
+    mut grid: ResMut<BlockGrid>,
+) {
+    if let Ok((block, position, hitpoints)) = query.get(trigger.entity()) {
+        let info = registry.get(block.0);
+        grid.insert(
+            *position,
+            GridCell {
+                entity: trigger.entity(),
+                block_type: block.0,
+                hitpoints: hitpoints.map_or(info.max_hitpoints, |hp| hp.0),
+            },
+        );
         if let Some(mut entity_commands) = commands.get_entity(trigger.entity()) {
             entity_commands.try_insert((
                 // Sprite::from_color(
@@ -198,7 +331,7 @@ fn on_add_block(
                 //     },
                 // ),
                 Sprite {
-                    image: block.0.image_handle(&assets),
+                    image: info.image.clone(),
                     custom_size: Some(Vec2 {
                         x: BLOCK_SIZE,
                         y: BLOCK_SIZE,
@@ -207,9 +340,14 @@ fn on_add_block(
                     // color: Color::srgba(1.5, 1.5, 1.5, 0.3),
                     ..Default::default()
                 },
-                HitPoints(block.0.max_hitpoints()),
             ));
-            // .add_child(crack);
+            // blocks restored from a chunk keep their damage
+            if hitpoints.is_none() {
+                entity_commands.try_insert(HitPoints::new(info.max_hitpoints));
+            }
+            if let Some(behaviour) = &info.behaviour {
+                behaviour.insert(&mut entity_commands);
+            }
         }
     }
 }
@@ -217,6 +355,104 @@ fn on_add_block(
 #[derive(Component)]
 pub struct DespawnHack;
 
+/// One of the two walls down the sides of the terrain.
+#[derive(Component)]
+pub struct Wall;
+
+pub fn on_block_broken(
+    trigger: Trigger<OnAdd, DespawnHack>,
+    query: Query<&BlockPosition>,
+    mut grid: ResMut<BlockGrid>,
+) {
+    // the entity lives until the next despawn_hack, but the cell is already empty
+    if let Ok(position) = query.get(trigger.entity()) {
+        grid.remove(*position, trigger.entity());
+    }
+}
+
+pub fn on_remove_block(
+    trigger: Trigger<OnRemove, Block>,
+    query: Query<&BlockPosition>,
+    mut grid: ResMut<BlockGrid>,
+) {
+    if let Ok(position) = query.get(trigger.entity()) {
+        grid.remove(*position, trigger.entity());
+    }
+}
+
+fn sync_block_grid(
+    query: Query<(&BlockPosition, &HitPoints), (Changed<HitPoints>, Without<DespawnHack>)>,
+    mut grid: ResMut<BlockGrid>,
+) {
+    for (position, hitpoints) in query.iter() {
+        if let Some(cell) = grid.0.get_mut(position) {
+            cell.hitpoints = hitpoints.0;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GridCell {
+    pub entity: Entity,
+    pub block_type: BlockType,
+    pub hitpoints: u16,
+}
+
+/// Loaded blocks indexed by cell. Broken and unloaded cells are absent.
+#[derive(Resource, Default, Debug)]
+pub struct BlockGrid(HashMap<BlockPosition, GridCell>);
+
+impl BlockGrid {
+    pub fn get(&self, column: usize, row: usize) -> Option<&GridCell> {
+        self.0.get(&BlockPosition { column, row })
+    }
+
+    /// Occupied cells in the 8 cells around `(column, row)`.
+    pub fn neighbours(
+        &self,
+        column: usize,
+        row: usize,
+    ) -> impl Iterator<Item = (BlockPosition, &GridCell)> {
+        self.around(column, row, 1)
+            .filter(move |(position, _)| position.column != column || position.row != row)
+    }
+
+    /// Occupied cells within `radius` cells (square) of `(column, row)`, including the centre.
+    pub fn around(
+        &self,
+        column: usize,
+        row: usize,
+        radius: usize,
+    ) -> impl Iterator<Item = (BlockPosition, &GridCell)> {
+        let columns = column.saturating_sub(radius)..=(column + radius).min(BLOCK_COUNT_WIDTH - 1);
+        let rows = row.saturating_sub(radius)..=row + radius;
+        rows.flat_map(move |row| {
+            columns
+                .clone()
+                .map(move |column| BlockPosition { column, row })
+        })
+        .filter_map(|position| self.0.get(&position).map(|cell| (position, cell)))
+    }
+
+    fn insert(&mut self, position: BlockPosition, cell: GridCell) {
+        self.0.insert(position, cell);
+    }
+
+    fn remove(&mut self, position: BlockPosition, entity: Entity) {
+        if self
+            .0
+            .get(&position)
+            .is_some_and(|cell| cell.entity == entity)
+        {
+            self.0.remove(&position);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
 fn despawn_hack(query: Query<Entity, With<DespawnHack>>, mut commands: Commands) {
     for entity in query.iter() {
         commands.entity(entity).try_despawn_recursive();
@@ -227,6 +463,18 @@ fn despawn_hack(query: Query<Entity, With<DespawnHack>>, mut commands: Commands)
 pub struct HitPoints(u16);
 
 impl HitPoints {
+    pub fn new(hitpoints: u16) -> Self {
+        Self(hitpoints)
+    }
+
+    pub fn current(&self) -> u16 {
+        self.0
+    }
+
+    pub fn heal(&mut self, amount: u16, max: u16) {
+        self.0 = self.0.saturating_add(amount).min(max);
+    }
+
     pub fn damage(&mut self, amount: u16) -> Result<u16, ()> {
         if self.0 <= amount {
             return Err(());
@@ -236,133 +484,328 @@ impl HitPoints {
     }
 }
 
-#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, EnumIter)]
-pub enum BlockType {
-    Blue,
-    LightBlue,
-    DarkBlue,
-    Purple,
-    LightPurple,
-    Pink,
-    Red,
-    Orange,
-}
-
-impl BlockType {
-    fn max_hitpoints(&self) -> u16 {
-        match self {
-            BlockType::Blue => 20,
-            BlockType::DarkBlue => 5,
-            BlockType::LightBlue => 1,
-            BlockType::Purple => 5,
-            BlockType::LightPurple => 6,
-            BlockType::Pink => 3,
-            BlockType::Red => 10,
-            BlockType::Orange => 20,
+/// Index of a block definition in the [`BlockRegistry`].
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct BlockType(pub usize);
+
+/// Mistake in one of the authored `.ron` files, found when it's loaded rather than mid-game.
+#[derive(Debug)]
+pub struct DefinitionError {
+    pub file: &'static str,
+    pub message: String,
+}
+
+impl DefinitionError {
+    pub fn new(file: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            file,
+            message: message.into(),
         }
     }
+}
+
+impl std::fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.file, self.message)
+    }
+}
 
-    pub fn colour(&self) -> Color {
-        match self {
-            BlockType::Blue => Color::srgb(0.145, 0.290, 0.725),
-            BlockType::DarkBlue => Color::srgb(0.098, 0.443, 0.675),
-            BlockType::LightBlue => Color::srgb(0.286, 0.663, 0.871),
-            BlockType::Purple => Color::srgb(0.459, 0.224, 0.784),
-            BlockType::LightPurple => Color::srgb(0.678, 0.212, 0.757),
-            BlockType::Red => Color::srgb(0.894, 0.333, 0.380),
-            BlockType::Pink => Color::srgb(0.565, 0.773, 0.357),
-            BlockType::Orange => Color::srgb(0.945, 0.663, 0.333),
-        }
+/// Block definitions as authored in `blocks.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct BlockDefinitions {
+    /// Noise used to wobble the depth bands so layers don't form straight lines.
+    pub blend: NoiseBlend,
+    pub blocks: Vec<BlockDefinition>,
+    /// Layers of the world from the surface down, each with its own block mix.
+    pub strata: Vec<StratumDefinition>,
+    #[serde(default)]
+    pub caves: Option<CaveLayer>,
+    #[serde(default)]
+    pub rooms: Option<RoomPlacement>,
+    #[serde(default)]
+    pub outposts: Option<OutpostPlacement>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BlockDefinition {
+    pub id: String,
+    /// File stem of a texture in the `block_textures` dynamic asset.
+    pub texture: String,
+    pub hitpoints: u16,
+    pub colour: (f32, f32, f32),
+    #[serde(default)]
+    pub vein: Option<OreVein>,
+    #[serde(default)]
+    pub behaviour: Option<BlockBehaviour>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum NoiseKind {
+    Fbm,
+    RidgedMulti,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct NoiseLayer {
+    pub kind: NoiseKind,
+    pub seed_offset: u32,
+    pub frequency: f64,
+    #[serde(default)]
+    pub lacunarity: Option<f64>,
+    #[serde(default)]
+    pub octaves: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct NoiseBlend {
+    pub noise: NoiseLayer,
+    pub amplitude: f32,
+}
+
+/// Exclusive depth range, measured in rows after blending.
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub struct DepthBand {
+    #[serde(default)]
+    pub min: Option<f32>,
+    #[serde(default)]
+    pub max: Option<f32>,
+}
+
+impl DepthBand {
+    pub fn contains(&self, depth: f32) -> bool {
+        self.min.is_none_or(|min| depth > min) && self.max.is_none_or(|max| depth < max)
+    }
+}
+
+/// Cells where the noise is above the threshold are left empty.
+#[derive(Deserialize, Debug, Clone)]
+pub struct CaveLayer {
+    pub noise: NoiseLayer,
+    pub threshold: f64,
+    #[serde(default)]
+    pub depth: DepthBand,
+}
+
+/// Where room templates get stamped, one room every `interval` rows from `first_row`.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct RoomPlacement {
+    pub first_row: usize,
+    pub interval: usize,
+}
+
+/// Shop rooms carved out of the terrain, one with its top at each of `rows`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct OutpostPlacement {
+    pub rows: Vec<usize>,
+    pub width: usize,
+    pub height: usize,
+    /// Upgrades each outpost sells, picked at random from the shop.
+    pub upgrades: usize,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OreVein {
+    /// Veins are checked in ascending priority, the first one above its threshold wins.
+    pub priority: u32,
+    pub noise: NoiseLayer,
+    pub threshold: f64,
+    #[serde(default)]
+    pub depth: DepthBand,
+}
+
+#[derive(Debug)]
+pub struct BlockInfo {
+    pub id: String,
+    pub image: Handle<Image>,
+    pub max_hitpoints: u16,
+    pub colour: Color,
+    pub behaviour: Option<BlockBehaviour>,
+}
+
+#[derive(Resource, Debug)]
+pub struct BlockRegistry {
+    blocks: Vec<BlockInfo>,
+    veins: Vec<(BlockType, OreVein)>,
+    strata: Vec<StratumInfo>,
+    blend: NoiseBlend,
+    caves: Option<CaveLayer>,
+    rooms: Vec<RoomInfo>,
+    room_placement: Option<RoomPlacement>,
+    outposts: Option<OutpostPlacement>,
+}
+
+impl FromWorld for BlockRegistry {
+    fn from_world(world: &mut World) -> Self {
+        let block_assets = world.resource::<BlockAssets>();
+        let definitions = world
+            .resource::<Assets<BlockDefinitions>>()
+            .get(&block_assets.definitions)
+            .expect("Block definitions should be loaded.");
+        let room_assets = world.resource::<Assets<RoomTemplate>>();
+        let rooms: Vec<&RoomTemplate> = block_assets
+            .rooms
+            .iter()
+            .map(|handle| room_assets.get(handle).expect("Rooms should be loaded."))
+            .collect();
+        let image_assets = world.resource::<GameImageAssets>();
+        BlockRegistry::new(definitions, &rooms, &image_assets.block_textures)
+            .unwrap_or_else(|error| panic!("{}", error))
     }
+}
 
-    fn image_handle(&self, assets: &Res<GameImageAssets>) -> Handle<Image> {
-        match self {
-            BlockType::Blue => assets.blue.clone(),
-            BlockType::DarkBlue => assets.dark_blue.clone(),
-            BlockType::LightBlue => assets.light_blue.clone(),
-            BlockType::Purple => assets.purple.clone(),
-            BlockType::LightPurple => assets.light_purple.clone(),
-            BlockType::Red => assets.red.clone(),
-            BlockType::Pink => assets.pink.clone(),
-            BlockType::Orange => assets.orange.clone(),
+pub const BLOCKS_FILE: &str = "blocks.ron";
+
+impl BlockRegistry {
+    /// Resolve every id in the block definitions and rooms, failing on anything that would
+    /// otherwise quietly leave a hole in the terrain.
+    pub fn new(
+        definitions: &BlockDefinitions,
+        rooms: &[&RoomTemplate],
+        textures: &bevy::utils::HashMap<AssetFileStem, Handle<Image>>,
+    ) -> Result<Self, DefinitionError> {
+        let mut blocks = Vec::new();
+        let mut veins = Vec::new();
+        for (i, definition) in definitions.blocks.iter().enumerate() {
+            let image = textures
+                .get(definition.texture.as_str())
+                .cloned()
+                .ok_or_else(|| {
+                    DefinitionError::new(
+                        BLOCKS_FILE,
+                        format!(
+                            "missing texture '{}' for block '{}'",
+                            definition.texture, definition.id
+                        ),
+                    )
+                })?;
+            let (r, g, b) = definition.colour;
+            blocks.push(BlockInfo {
+                id: definition.id.clone(),
+                image,
+                max_hitpoints: definition.hitpoints,
+                colour: Color::srgb(r, g, b),
+                behaviour: definition.behaviour,
+            });
+            if let Some(vein) = &definition.vein {
+                veins.push((BlockType(i), vein.clone()));
+            }
+        }
+        veins.sort_by_key(|(_, vein)| vein.priority);
+
+        let mut registry = Self {
+            blocks,
+            veins,
+            strata: Vec::new(),
+            blend: definitions.blend.clone(),
+            caves: definitions.caves.clone(),
+            rooms: Vec::new(),
+            room_placement: definitions.rooms,
+            outposts: definitions.outposts.clone(),
+        };
+        registry.strata = definitions
+            .strata
+            .iter()
+            .map(|definition| StratumInfo::new(definition, &registry))
+            .collect::<Result<_, _>>()?;
+        if registry.strata.is_empty() {
+            return Err(DefinitionError::new(
+                BLOCKS_FILE,
+                "needs at least one stratum",
+            ));
         }
+        registry.strata.sort_by(|a, b| {
+            a.max_depth
+                .unwrap_or(f32::INFINITY)
+                .total_cmp(&b.max_depth.unwrap_or(f32::INFINITY))
+        });
+        registry.rooms = rooms
+            .iter()
+            .map(|template| RoomInfo::new(template, &registry))
+            .collect::<Result<_, _>>()?;
+        Ok(registry)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = BlockType> {
+        (0..self.blocks.len()).map(BlockType)
+    }
+
+    pub fn get(&self, block_type: BlockType) -> &BlockInfo {
+        &self.blocks[block_type.0]
+    }
+
+    pub fn find(&self, id: &str) -> Option<BlockType> {
+        self.blocks
+            .iter()
+            .position(|block| block.id == id)
+            .map(BlockType)
+    }
+
+    /// Look up a block id authored in `file`, so a typo fails the load instead of being skipped.
+    pub fn resolve(&self, file: &'static str, id: &str) -> Result<BlockType, DefinitionError> {
+        self.find(id)
+            .ok_or_else(|| DefinitionError::new(file, format!("unknown block id '{}'", id)))
+    }
+
+    /// Build a cost table from block ids and counts authored in `file`.
+    pub fn cost(
+        &self,
+        file: &'static str,
+        items: &[(String, u32)],
+    ) -> Result<HashMap<BlockType, u32>, DefinitionError> {
+        items
+            .iter()
+            .map(|(id, count)| Ok((self.resolve(file, id)?, *count)))
+            .collect()
+    }
+
+    pub fn blend(&self) -> (&NoiseLayer, f32) {
+        (&self.blend.noise, self.blend.amplitude)
+    }
+
+    pub fn veins(&self) -> &[(BlockType, OreVein)] {
+        &self.veins
+    }
+
+    pub fn caves(&self) -> Option<&CaveLayer> {
+        self.caves.as_ref()
+    }
+
+    pub fn rooms(&self) -> &[RoomInfo] {
+        &self.rooms
+    }
+
+    pub fn room_placement(&self) -> Option<RoomPlacement> {
+        self.room_placement
+    }
+
+    pub fn outposts(&self) -> Option<&OutpostPlacement> {
+        self.outposts.as_ref()
+    }
+
+    pub fn strata(&self) -> &[StratumInfo] {
+        &self.strata
+    }
+
+    /// Index of the stratum containing `depth`, the last one fills everything below.
+    pub fn stratum_at(&self, depth: f32) -> usize {
+        self.strata
+            .iter()
+            .position(|stratum| stratum.max_depth.is_none_or(|max| depth < max))
+            .unwrap_or(self.strata.len() - 1)
     }
 }
 
-fn pick_block_type(position: Vec2, seed: u32) -> BlockType {
-    let a = Fbm::<Perlin>::new(seed + 123)
-        .set_frequency(0.4)
-        .get([position.x as f64, position.y as f64]);
-    let b = Fbm::<Perlin>::new(seed + 12412)
-        .set_frequency(0.04)
-        .get([position.x as f64, position.y as f64]);
-    let c = Fbm::<Perlin>::new(seed + 123546)
-        .set_frequency(0.08)
-        .set_lacunarity(2.5)
-        .set_octaves(3)
-        .get([position.x as f64, position.y as f64]);
-    let d = Fbm::<Perlin>::new(seed + 77212)
-        .set_frequency(0.06)
-        .get([position.x as f64, position.y as f64]);
-    let e = Fbm::<Perlin>::new(seed + 124363)
-        .set_frequency(0.04)
-        .get([position.x as f64, position.y as f64]);
-
-    let f = RidgedMulti::<Perlin>::new(seed + 361232412)
-        .set_frequency(0.04)
-        //.set_octaves(3)
-        .get([position.x as f64, position.y as f64]);
-    let g = RidgedMulti::<Perlin>::new(seed + 6266123)
-        .set_frequency(0.02)
-        //.set_octaves(3)
-        .get([position.x as f64, position.y as f64]);
-    let blend = 10.0
-        * Fbm::<Perlin>::new(seed + 123)
-            .set_frequency(0.4)
-            .get([position.x as f64, position.y as f64]) as f32;
-
-    if a > 0.65 && blend + position.y < 100.0 {
-        BlockType::Blue
-    } else if b > 0.65 && blend + position.y > 50.0 {
-        BlockType::LightBlue
-    } else if c > 0.65 {
-        BlockType::LightPurple
-    } else if d > 0.64 {
-        BlockType::Red
-    } else if e > 0.62 && blend + position.y > 50.0 {
-        BlockType::Orange
-    } else if f > 0.7 {
-        BlockType::Purple
-    } else if g > 0.68 && blend + position.y > 50.0 {
-        BlockType::Pink
-    } else {
-        pick_base_block_type(position, seed)
-    }
-}
-
-fn pick_base_block_type(position: Vec2, seed: u32) -> BlockType {
-    let blend = 10.0
-        * Fbm::<Perlin>::new(seed + 123)
-            .set_frequency(0.4)
-            .get([position.x as f64, position.y as f64]) as f32;
-    if blend + position.y < 50.0 {
-        BlockType::LightBlue
-    } else if blend + position.y < 100.0 {
-        BlockType::DarkBlue
-    } else {
-        BlockType::Blue
-    }
-}
-
-pub fn block_break(block_type: BlockType, transform: &Transform, commands: &mut Commands) {
+pub fn block_break(
+    block_type: BlockType,
+    transform: &Transform,
+    commands: &mut Commands,
+    registry: &BlockRegistry,
+) {
     const NUM_PARTICLES: usize = 10;
     let mut rng = rand::rng();
-    let bloom_color = Color::srgba(
-        block_type.colour().to_srgba().red * 1.1,
-        block_type.colour().to_srgba().green * 1.1,
-        block_type.colour().to_srgba().blue * 1.1,
-        1.0,
-    );
+    let colour = registry.get(block_type).colour.to_srgba();
+    let bloom_color = Color::srgba(colour.red * 1.1, colour.green * 1.1, colour.blue * 1.1, 1.0);
 
     for i in 0..NUM_PARTICLES {
         let angle = i as f32 * (std::f32::consts::PI * 2.0 / NUM_PARTICLES as f32);
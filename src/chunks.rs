@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    app_state::AppState,
+    ball::Ball,
+    behaviours::RegenerationTimer,
+    blocks::{
+        BLOCK_COUNT_WIDTH, Block, BlockPosition, BlockType, DespawnHack, HitPoints, block_bundle,
+        row_at,
+    },
+};
+
+pub struct ChunksPlugin;
+
+impl Plugin for ChunksPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UnloadedChunks>()
+            .add_systems(OnEnter(AppState::Game), reset_chunks)
+            .add_systems(
+                FixedUpdate,
+                (unload_chunks, restore_chunks)
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
+            );
+    }
+}
+
+pub const CHUNK_ROWS: usize = 16;
+// Unload further away than we restore so chunks don't flicker on the boundary.
+const UNLOAD_DISTANCE: i32 = 3 * CHUNK_ROWS as i32;
+const RESTORE_DISTANCE: i32 = 2 * CHUNK_ROWS as i32;
+
+/// What's kept of a block while its chunk is unloaded.
+#[derive(Debug, Clone)]
+struct SavedBlock {
+    block_type: BlockType,
+    hitpoints: HitPoints,
+    /// Regenerating blocks carry on healing from where they left off.
+    regeneration: Option<RegenerationTimer>,
+}
+
+/// Saved cells of a chunk, `None` for cells that were broken.
+type ChunkCells = Vec<Option<SavedBlock>>;
+
+pub type ChunkBlockQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Block,
+        &'static HitPoints,
+        &'static BlockPosition,
+        Option<&'static RegenerationTimer>,
+    ),
+    Without<DespawnHack>,
+>;
+
+/// Chunks that were despawned because nothing was near them.
+#[derive(Resource, Default)]
+pub struct UnloadedChunks(HashMap<usize, ChunkCells>);
+
+pub fn chunk_of(row: usize) -> usize {
+    row / CHUNK_ROWS
+}
+
+fn reset_chunks(mut unloaded: ResMut<UnloadedChunks>) {
+    unloaded.0.clear();
+}
+
+/// The highest row that the camera or any ball can currently see.
+fn topmost_active_row(
+    camera_query: &Query<(&Camera, &GlobalTransform)>,
+    balls_query: &Query<&Transform, With<Ball>>,
+) -> i32 {
+    let (camera, camera_transform) = camera_query
+        .get_single()
+        .expect("Need single camera to check chunks.");
+    let viewport_top = camera
+        .viewport_to_world_2d(camera_transform, Vec2::ZERO)
+        .expect("Need viewport position to check chunks.");
+
+    balls_query
+        .iter()
+        .map(|ball| row_at(ball.translation.y))
+        .fold(row_at(viewport_top.y), i32::min)
+}
+
+fn chunk_bottom_row(chunk: usize) -> i32 {
+    ((chunk + 1) * CHUNK_ROWS) as i32
+}
+
+fn unload_chunks(
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    balls_query: Query<&Transform, With<Ball>>,
+    block_query: ChunkBlockQuery,
+    mut unloaded: ResMut<UnloadedChunks>,
+    mut commands: Commands,
+) {
+    let top_row = topmost_active_row(&camera_query, &balls_query);
+    if top_row < UNLOAD_DISTANCE {
+        return;
+    }
+
+    let to_unload: Vec<usize> = (0..chunk_of((top_row - UNLOAD_DISTANCE) as usize))
+        .filter(|chunk| !unloaded.0.contains_key(chunk))
+        .collect();
+    if to_unload.is_empty() {
+        return;
+    }
+    unload(&to_unload, &block_query, &mut unloaded, &mut commands);
+}
+
+/// Save and despawn every block in `chunks`.
+pub fn unload(
+    chunks: &[usize],
+    block_query: &ChunkBlockQuery,
+    unloaded: &mut UnloadedChunks,
+    commands: &mut Commands,
+) {
+    for chunk in chunks {
+        unloaded
+            .0
+            .insert(*chunk, vec![None; CHUNK_ROWS * BLOCK_COUNT_WIDTH]);
+    }
+    for (entity, block, hitpoints, position, regeneration) in block_query.iter() {
+        let chunk = chunk_of(position.row);
+        if !chunks.contains(&chunk) {
+            continue;
+        }
+        if let Some(cells) = unloaded.0.get_mut(&chunk) {
+            let index = (position.row % CHUNK_ROWS) * BLOCK_COUNT_WIDTH + position.column;
+            cells[index] = Some(SavedBlock {
+                block_type: block.0,
+                hitpoints: *hitpoints,
+                regeneration: regeneration.cloned(),
+            });
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+    debug!("Unloaded chunks {:?}", chunks);
+}
+
+fn restore_chunks(
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    balls_query: Query<&Transform, With<Ball>>,
+    mut unloaded: ResMut<UnloadedChunks>,
+    mut commands: Commands,
+) {
+    if unloaded.0.is_empty() {
+        return;
+    }
+    let top_row = topmost_active_row(&camera_query, &balls_query);
+
+    let to_restore: Vec<usize> = unloaded
+        .0
+        .keys()
+        .copied()
+        .filter(|chunk| chunk_bottom_row(*chunk) + RESTORE_DISTANCE > top_row)
+        .collect();
+    for chunk in to_restore {
+        restore(chunk, &mut unloaded, &mut commands);
+    }
+}
+
+/// Respawn the saved blocks of `chunk`, if it was unloaded.
+pub fn restore(chunk: usize, unloaded: &mut UnloadedChunks, commands: &mut Commands) {
+    let Some(cells) = unloaded.0.remove(&chunk) else {
+        return;
+    };
+    for (index, cell) in cells.into_iter().enumerate() {
+        let Some(saved) = cell else {
+            continue;
+        };
+        let row = chunk * CHUNK_ROWS + index / BLOCK_COUNT_WIDTH;
+        let column = index % BLOCK_COUNT_WIDTH;
+        let mut entity_commands =
+            commands.spawn((block_bundle(column, row, saved.block_type), saved.hitpoints));
+        if let Some(regeneration) = saved.regeneration {
+            entity_commands.insert(regeneration);
+        }
+    }
+    debug!("Restored chunk {}", chunk);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use crate::{blocks::HitPoints, harness::Harness};
+
+    #[test]
+    fn unloaded_chunk_is_restored_with_damage_and_regeneration() {
+        let mut harness = Harness::new();
+        harness.world_mut().init_resource::<UnloadedChunks>();
+        let block = harness.spawn_block(3, 2, "regen");
+        harness
+            .world_mut()
+            .entity_mut(block)
+            .insert(HitPoints::new(3));
+        // part way to the next heal
+        harness.step(20);
+        let elapsed = harness
+            .world()
+            .get::<RegenerationTimer>(block)
+            .unwrap()
+            .0
+            .elapsed();
+        assert!(!elapsed.is_zero());
+
+        let chunk = chunk_of(2);
+        harness
+            .world_mut()
+            .run_system_once(
+                move |query: ChunkBlockQuery,
+                      mut unloaded: ResMut<UnloadedChunks>,
+                      mut commands: Commands| {
+                    unload(&[chunk], &query, &mut unloaded, &mut commands);
+                },
+            )
+            .unwrap();
+        assert!(harness.world().get_entity(block).is_err());
+
+        harness
+            .world_mut()
+            .run_system_once(
+                move |mut unloaded: ResMut<UnloadedChunks>, mut commands: Commands| {
+                    restore(chunk, &mut unloaded, &mut commands);
+                },
+            )
+            .unwrap();
+        harness.world_mut().flush();
+        let restored = harness
+            .world_mut()
+            .query::<(Entity, &BlockPosition)>()
+            .iter(harness.world())
+            .find(|(_, position)| position.column == 3 && position.row == 2)
+            .map(|(entity, _)| entity)
+            .expect("block was restored");
+        assert_eq!(harness.hitpoints(restored), Some(3));
+        let timer = &harness
+            .world()
+            .get::<RegenerationTimer>(restored)
+            .unwrap()
+            .0;
+        assert_eq!(timer.elapsed(), elapsed);
+    }
+}
@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::{
+    Collider, CollisionGroups, ExternalImpulse, QueryFilter, RapierContext, ReadMassProperties,
+    ReadRapierContext,
+};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    app_state::AppState,
+    paddle::{Paddle, PaddleAction},
+    physics::{BLOCK_GROUP, PADDLE_GROUP},
+    shop::ShopStats,
+    statsbar::UpdateStatsBarDashEvent,
+};
+
+pub struct DashPlugin;
+
+impl Plugin for DashPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, dash.run_if(in_state(AppState::Game)));
+    }
+}
+
+/// Seconds a dash lasts, the paddle passes through blocks for this long and then until it's
+/// clear of them.
+const DASH_TIME: f32 = 0.2;
+
+/// Short burst of speed in the move direction.
+#[derive(Component, Debug, Default)]
+pub struct Dash {
+    /// Seconds left of the current dash.
+    pub remaining: f32,
+    /// Seconds until the paddle can dash again.
+    pub cooldown: f32,
+}
+
+impl Dash {
+    pub fn active(&self) -> bool {
+        self.remaining > 0.0
+    }
+}
+
+type DashQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static ActionState<PaddleAction>,
+        &'static mut Dash,
+        &'static mut ExternalImpulse,
+        &'static ReadMassProperties,
+        &'static mut CollisionGroups,
+        &'static Transform,
+        &'static Collider,
+    ),
+    With<Paddle>,
+>;
+
+fn dash(
+    mut query: DashQuery,
+    stats: Res<ShopStats>,
+    time: Res<Time>,
+    rapier_context: ReadRapierContext,
+    mut commands: Commands,
+) {
+    let Ok((action_state, mut dash, mut impulse, mass, mut groups, transform, collider)) =
+        query.get_single_mut()
+    else {
+        return;
+    };
+    let delta = time.delta_secs();
+    let cooldown = dash.cooldown;
+
+    dash.cooldown = (dash.cooldown - delta).max(0.0);
+    if dash.active() {
+        let remaining = dash.remaining - delta;
+        if remaining > 0.0 {
+            dash.remaining = remaining;
+        } else if !overlaps_block(&rapier_context.single(), transform, collider) {
+            dash.remaining = 0.0;
+            groups.filters |= BLOCK_GROUP;
+        }
+        // otherwise keep dashing, turning blocks back on now would leave the paddle stuck in one
+    }
+
+    let direction = action_state.clamped_axis_pair(&PaddleAction::Move);
+    if action_state.just_pressed(&PaddleAction::Dash)
+        && dash.cooldown == 0.0
+        && direction != Vec2::ZERO
+    {
+        impulse.impulse = direction.normalize() * stats.dash_speed() * mass.get().mass;
+        dash.remaining = DASH_TIME;
+        dash.cooldown = stats.dash_cooldown();
+        // blocks can't stop or hurt the paddle mid-dash
+        groups.filters &= !BLOCK_GROUP;
+    }
+
+    if dash.cooldown != cooldown {
+        commands.trigger(UpdateStatsBarDashEvent {
+            cooldown: dash.cooldown,
+        });
+    }
+}
+
+fn overlaps_block(context: &RapierContext, transform: &Transform, collider: &Collider) -> bool {
+    let filter = QueryFilter::new()
+        .exclude_sensors()
+        .groups(CollisionGroups::new(PADDLE_GROUP, BLOCK_GROUP));
+    let mut overlapping = false;
+    context.intersections_with_shape(
+        transform.translation.truncate(),
+        transform.rotation.to_euler(EulerRot::XYZ).2,
+        collider,
+        filter,
+        |_| {
+            overlapping = true;
+            false
+        },
+    );
+    overlapping
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use leafwing_input_manager::prelude::Buttonlike;
+
+    use crate::{
+        blocks::BLOCK_SIZE,
+        harness::{Harness, cell_centre},
+    };
+
+    #[test]
+    fn dash_carries_the_paddle_through_blocks() {
+        let mut harness = Harness::new();
+        let block = cell_centre(22, 0);
+        harness.spawn_block(22, 0, "blue");
+        harness.set_paddle_position(cell_centre(20, 0));
+        let paddle = harness.paddle();
+
+        KeyCode::KeyD.press(harness.world_mut());
+        harness.step(60);
+        let x = harness
+            .world()
+            .get::<Transform>(paddle)
+            .unwrap()
+            .translation
+            .x;
+        assert!(x < block.x - BLOCK_SIZE, "{}", x);
+
+        harness.tap(KeyCode::ShiftLeft);
+        harness.step(30);
+        let x = harness
+            .world()
+            .get::<Transform>(paddle)
+            .unwrap()
+            .translation
+            .x;
+        assert!(x > block.x + BLOCK_SIZE, "{}", x);
+        assert!(harness.world().get::<Dash>(paddle).unwrap().cooldown > 0.0);
+        let groups = harness.world().get::<CollisionGroups>(paddle).unwrap();
+        assert!(groups.filters.contains(BLOCK_GROUP));
+    }
+
+    #[test]
+    fn dash_keeps_going_until_the_paddle_is_clear_of_blocks() {
+        let mut harness = Harness::new();
+        // longer than a dash on its own covers
+        for column in 21..=30 {
+            harness.spawn_block(column, 0, "blue");
+        }
+        let end = cell_centre(30, 0);
+        harness.set_paddle_position(cell_centre(19, 0));
+        let paddle = harness.paddle();
+
+        KeyCode::KeyD.press(harness.world_mut());
+        harness.step(1);
+        harness.tap(KeyCode::ShiftLeft);
+        harness.step(60);
+        let x = harness
+            .world()
+            .get::<Transform>(paddle)
+            .unwrap()
+            .translation
+            .x;
+        assert!(x > end.x + BLOCK_SIZE, "{}", x);
+        let groups = harness.world().get::<CollisionGroups>(paddle).unwrap();
+        assert!(groups.filters.contains(BLOCK_GROUP));
+    }
+}
@@ -0,0 +1,403 @@
+//! Headless app for testing the game loop without a window or GPU.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::{
+    asset::{AssetPath, LoadState},
+    gizmos::GizmoPlugin,
+    input::InputPlugin,
+    scene::ScenePlugin,
+    state::app::StatesPlugin,
+    time::TimeUpdateStrategy,
+};
+use bevy_asset_loader::{
+    asset_collection::AssetCollectionApp,
+    mapped::{AssetFileStem, MapKey},
+};
+use bevy_hui::prelude::HuiPlugin;
+use bevy_rapier2d::prelude::{TimestepMode, Velocity};
+use leafwing_input_manager::prelude::Buttonlike;
+
+use crate::{
+    aim::AimPlugin,
+    app_state::AppState,
+    asset_loading::{GameImageAssets, UiComponentAssets},
+    ball::{Ball, BallPlugin, CollectedResources},
+    behaviours::BlockBehavioursPlugin,
+    blocks::{
+        BLOCK_GROUP_OFFSET, BLOCK_SIZE, BlockDefinitions, BlockGrid, BlockRegistry, BlockType,
+        HitPoints, block_bundle, on_add_block, on_block_broken, on_remove_block,
+    },
+    dash::DashPlugin,
+    loadout::{BallKind, Loadout, LoadoutPlugin},
+    magnet::MagnetPlugin,
+    paddle::{Paddle, PaddlePlugin},
+    physics::PhysicsPlugin,
+    pickups::PickupsPlugin,
+    refinery::{RefineryDefinitions, RefineryPlugin, RefineryRecipes},
+    seed::Seed,
+    shop::{ShopPlugin, ShopStats, UpgradeDefinitions},
+    shopmenu::ShopMenuPlugin,
+    shoppanel::{PanelKind, ShopPanel},
+};
+
+/// Fixed step used for both `FixedUpdate` and rapier, so one `step` is one physics tick.
+const TIMESTEP: Duration = Duration::from_micros(15625);
+
+pub struct Harness {
+    pub app: App,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Harness {
+    /// App in `AppState::Game` with the paddle spawned but no terrain.
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            StatesPlugin,
+            InputPlugin,
+            TransformPlugin,
+            HierarchyPlugin,
+            AssetPlugin::default(),
+            ScenePlugin,
+        ))
+        // rapier's async colliders need meshes registered
+        .init_asset::<Mesh>()
+        // and gizmos need shaders, even though nothing is drawn
+        .init_asset::<Shader>()
+        .add_plugins(GizmoPlugin)
+        // the shop menu templates build image nodes from atlases
+        .init_asset::<TextureAtlasLayout>()
+        .add_plugins(HuiPlugin)
+        // no window, but the aim still listens for the mouse
+        .add_event::<CursorMoved>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(TIMESTEP))
+        .insert_resource(Time::<Fixed>::from_duration(TIMESTEP))
+        .insert_resource(TimestepMode::Fixed {
+            dt: TIMESTEP.as_secs_f32(),
+            substeps: 1,
+        })
+        .insert_resource(Seed(1))
+        .insert_resource(registry())
+        .insert_resource(shop_stats())
+        .insert_resource(refinery_recipes())
+        .insert_resource(GameImageAssets::default())
+        .init_collection::<UiComponentAssets>()
+        // no terrain, but blocks spawned by tests still go in the grid
+        .init_resource::<BlockGrid>()
+        .add_observer(on_add_block)
+        .add_observer(on_block_broken)
+        .add_observer(on_remove_block)
+        .init_state::<AppState>()
+        .add_plugins((
+            PaddlePlugin,
+            AimPlugin,
+            BallPlugin,
+            LoadoutPlugin,
+            MagnetPlugin,
+            DashPlugin,
+            PickupsPlugin,
+            PhysicsPlugin,
+            ShopPlugin,
+            ShopMenuPlugin,
+            RefineryPlugin,
+            BlockBehavioursPlugin,
+        ));
+
+        wait_for_templates(&mut app);
+        app.world_mut()
+            .spawn((Camera2d, OrthographicProjection::default_2d()));
+        app.world_mut()
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Game);
+        app.update();
+
+        Self { app }
+    }
+
+    pub fn world(&self) -> &World {
+        self.app.world()
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    /// Run `count` frames, each advancing `FixedUpdate` and rapier by one tick.
+    pub fn step(&mut self, count: usize) {
+        for _ in 0..count {
+            self.app.update();
+        }
+    }
+
+    pub fn block_type(&self, id: &str) -> BlockType {
+        self.world()
+            .resource::<BlockRegistry>()
+            .find(id)
+            .expect("Unknown block id.")
+    }
+
+    /// Spawn a block of type `id` at full hitpoints, with its behaviour.
+    pub fn spawn_block(&mut self, column: usize, row: usize, id: &str) -> Entity {
+        let block_type = self.block_type(id);
+        let block = self
+            .world_mut()
+            .spawn(block_bundle(column, row, block_type))
+            .id();
+        // let on_add_block fill in the hitpoints and behaviour
+        self.world_mut().flush();
+        block
+    }
+
+    /// Unlock every ball kind and make `kind` the next one fired.
+    pub fn select_ball(&mut self, kind: BallKind) {
+        let mut loadout = self.world_mut().resource_mut::<Loadout>();
+        loadout.unlock(BallKind::ALL.len());
+        while loadout.selected() != kind {
+            loadout.next();
+        }
+    }
+
+    pub fn paddle(&mut self) -> Entity {
+        self.world_mut()
+            .query_filtered::<Entity, With<Paddle>>()
+            .single(self.app.world())
+    }
+
+    pub fn set_paddle_position(&mut self, position: Vec2) {
+        let paddle = self.paddle();
+        let mut transform = self
+            .world_mut()
+            .get_mut::<Transform>(paddle)
+            .expect("Paddle has a transform.");
+        transform.translation = position.extend(0.0);
+    }
+
+    /// Press a key for one frame, like a player tapping it.
+    pub fn tap(&mut self, key: KeyCode) {
+        key.press(self.world_mut());
+        self.step(1);
+        key.release(self.world_mut());
+        self.step(1);
+    }
+
+    pub fn fire(&mut self) {
+        self.tap(KeyCode::Space);
+    }
+
+    pub fn balls(&mut self) -> Vec<Entity> {
+        self.world_mut()
+            .query_filtered::<Entity, With<Ball>>()
+            .iter(self.app.world())
+            .collect()
+    }
+
+    pub fn ball_velocity(&mut self, ball: Entity) -> Vec2 {
+        self.world()
+            .get::<Velocity>(ball)
+            .expect("Ball has a velocity.")
+            .linvel
+    }
+
+    pub fn hitpoints(&self, block: Entity) -> Option<u16> {
+        self.world().get::<HitPoints>(block).map(HitPoints::current)
+    }
+
+    /// Give the paddle `count` of each block `id`.
+    pub fn with_resources(mut self, resources: &[(&str, u32)]) -> Self {
+        let paddle = self.paddle();
+        for &(id, count) in resources {
+            let block_type = self.block_type(id);
+            self.world_mut()
+                .get_mut::<CollectedResources>(paddle)
+                .expect("Paddle collects resources.")
+                .add_many(block_type, count);
+        }
+        self
+    }
+
+    /// Enabled panel of `kind`, as if the paddle were flying over it.
+    pub fn spawn_shop_panel(&mut self, kind: PanelKind) -> Entity {
+        let upgrade = match kind {
+            PanelKind::Upgrade(item) => self.world().resource::<ShopStats>().name(item),
+            PanelKind::Refresh => "Refresh",
+            PanelKind::Respec => "Respec",
+        }
+        .to_string();
+        self.world_mut()
+            .spawn(ShopPanel {
+                enabled: true,
+                kind,
+                upgrade,
+            })
+            .id()
+    }
+
+    pub fn collected(&self, entity: Entity, id: &str) -> u32 {
+        let block_type = self.block_type(id);
+        self.world()
+            .get::<CollectedResources>(entity)
+            .and_then(|resources| resources.counts.get(&block_type).copied())
+            .unwrap_or(0)
+    }
+}
+
+fn shop_stats() -> ShopStats {
+    let definitions: UpgradeDefinitions = bevy::asset::ron::from_str(include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/upgrades.ron"
+    )))
+    .expect("upgrades.ron should parse");
+    ShopStats::new(&definitions, &registry()).expect("upgrades.ron should be valid")
+}
+
+fn refinery_recipes() -> RefineryRecipes {
+    let definitions: RefineryDefinitions = bevy::asset::ron::from_str(include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/refinery.ron"
+    )))
+    .expect("refinery.ron should parse");
+    RefineryRecipes::new(&definitions, &registry()).expect("refinery.ron should be valid")
+}
+
+/// World position of the centre of a block cell.
+pub fn cell_centre(column: usize, row: usize) -> Vec2 {
+    Vec2::new(
+        -BLOCK_GROUP_OFFSET + column as f32 * BLOCK_SIZE + BLOCK_SIZE / 2.0,
+        row as f32 * -BLOCK_SIZE + BLOCK_SIZE / 2.0,
+    )
+}
+
+pub fn block_definitions() -> BlockDefinitions {
+    bevy::asset::ron::from_str(include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/blocks.ron"
+    )))
+    .expect("blocks.ron should parse")
+}
+
+pub fn registry() -> BlockRegistry {
+    registry_from(&block_definitions())
+}
+
+/// Registry without rooms.
+pub fn registry_from(definitions: &BlockDefinitions) -> BlockRegistry {
+    BlockRegistry::new(definitions, &[], &block_textures(definitions))
+        .expect("blocks.ron should be valid")
+}
+
+/// Update until the shop menu templates are loaded from `assets/ui`, as the loading state would.
+fn wait_for_templates(app: &mut App) {
+    let ui = app.world().resource::<UiComponentAssets>();
+    let handles = [
+        ui.shop.clone().untyped(),
+        ui.shop_item.clone().untyped(),
+        ui.resource.clone().untyped(),
+    ];
+    loop {
+        let server = app.world().resource::<AssetServer>();
+        if handles
+            .iter()
+            .all(|handle| server.is_loaded_with_dependencies(handle))
+        {
+            return;
+        }
+        assert!(
+            !handles
+                .iter()
+                .any(|handle| matches!(server.load_state(handle), LoadState::Failed(_))),
+            "Shop menu templates failed to load."
+        );
+        app.update();
+    }
+}
+
+/// A placeholder for every texture the blocks use.
+pub fn block_textures(
+    definitions: &BlockDefinitions,
+) -> bevy::utils::HashMap<AssetFileStem, Handle<Image>> {
+    definitions
+        .blocks
+        .iter()
+        .map(|block| {
+            let path = AssetPath::from(format!("textures/{}.png", block.texture));
+            (AssetFileStem::from_asset_path(&path), Handle::default())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{ball::BallStats, blocks::DespawnHack};
+
+    #[test]
+    fn fire_spawns_one_ball_with_a_downward_velocity() {
+        let mut harness = Harness::new();
+        assert!(harness.balls().is_empty());
+
+        harness.fire();
+        let balls = harness.balls();
+        assert_eq!(balls.len(), 1);
+        assert!(harness.ball_velocity(balls[0]).y < 0.0);
+    }
+
+    #[test]
+    fn ball_damages_block_below_paddle() {
+        let mut harness = Harness::new();
+        let block = harness.spawn_block(20, 4, "blue");
+        let start = harness.hitpoints(block).unwrap();
+        harness.set_paddle_position(cell_centre(20, 0));
+
+        harness.fire();
+        let ball = harness.balls()[0];
+        let damage = harness.world().get::<BallStats>(ball).unwrap().damage;
+        harness.step(120);
+
+        let hitpoints = harness.hitpoints(block).unwrap();
+        assert!(hitpoints <= start - damage, "{} -> {}", start, hitpoints);
+    }
+
+    #[test]
+    fn broken_block_is_collected_by_ball_then_paddle() {
+        let mut harness = Harness::new();
+        // light blue breaks in a single hit
+        let block = harness.spawn_block(20, 4, "light_blue");
+        harness.set_paddle_position(cell_centre(20, 0));
+
+        harness.fire();
+        let ball = harness.balls()[0];
+        let mut broken = false;
+        for _ in 0..120 {
+            harness.step(1);
+            if harness.world().get::<DespawnHack>(block).is_some()
+                || harness.world().get_entity(block).is_err()
+            {
+                broken = true;
+                break;
+            }
+        }
+        assert!(broken, "block never broke");
+        harness.step(1);
+        assert_eq!(harness.collected(ball, "light_blue"), 1);
+
+        // the ball comes back up and hands its resources to the paddle
+        let paddle = harness.paddle();
+        for _ in 0..240 {
+            if harness.collected(paddle, "light_blue") > 0 {
+                break;
+            }
+            harness.step(1);
+        }
+        assert_eq!(harness.collected(paddle, "light_blue"), 1);
+        assert_eq!(harness.collected(ball, "light_blue"), 0);
+    }
+}
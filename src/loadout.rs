@@ -0,0 +1,454 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+use leafwing_input_manager::prelude::ActionState;
+use rand::{Rng, seq::IteratorRandom};
+
+use crate::{
+    app_state::AppState,
+    asset_loading::GameImageAssets,
+    ball::{Ball, BallStats, spawn_ball},
+    behaviours::BlockDamageEvent,
+    blocks::{Block, BlockGrid, BlockPosition},
+    paddle::{Paddle, PaddleAction},
+    particles::BoxParticlesEvent,
+    shop::{ShopItem, ShopStats, UpgradeLevelChangedEvent},
+    statsbar::UpdateStatsBarBallKindEvent,
+};
+
+pub struct LoadoutPlugin;
+
+impl Plugin for LoadoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Loadout>()
+            .add_systems(FixedUpdate, cycle_loadout.run_if(in_state(AppState::Game)))
+            .add_observer(on_ball_hit_block)
+            .add_observer(on_new_ball_upgrade);
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BallKind {
+    Basic,
+    /// Twice the size of a basic ball.
+    Big,
+    /// Splits into three on its first hit.
+    Multi,
+    /// Damages every block around the one it hits.
+    Bomb,
+    /// Arcs to a few nearby blocks on every hit.
+    Lightning,
+    /// Sometimes doubles the resources from broken blocks.
+    Silver,
+    /// Always doubles the resources from broken blocks.
+    Gold,
+    /// Hits twice as hard and moves faster.
+    Pro,
+}
+
+const BOMB_RADIUS: usize = 1;
+const LIGHTNING_RANGE: usize = 3;
+const LIGHTNING_ARCS: usize = 3;
+const MULTI_SPREAD: f32 = 20.0;
+const SILVER_DOUBLE_CHANCE: f64 = 0.5;
+
+impl BallKind {
+    /// In the order the new ball upgrade unlocks them.
+    pub const ALL: [BallKind; 8] = [
+        BallKind::Basic,
+        BallKind::Big,
+        BallKind::Multi,
+        BallKind::Bomb,
+        BallKind::Lightning,
+        BallKind::Silver,
+        BallKind::Gold,
+        BallKind::Pro,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BallKind::Basic => "Basic",
+            BallKind::Bomb => "Bomb",
+            BallKind::Lightning => "Lightning",
+            BallKind::Big => "Big",
+            BallKind::Multi => "Multi",
+            BallKind::Gold => "Gold",
+            BallKind::Silver => "Silver",
+            BallKind::Pro => "Pro",
+        }
+    }
+
+    /// File stem of the icon in the `ball_icons` dynamic asset.
+    pub fn icon(&self) -> &'static str {
+        match self {
+            BallKind::Basic => "ICON_basic_ball",
+            BallKind::Bomb => "ICON_bomb_ball",
+            BallKind::Lightning => "ICON_lightening_ball",
+            BallKind::Big => "ICON_big_ball",
+            BallKind::Multi => "ICON_multi_ball1",
+            BallKind::Gold => "ICON_gold_ball",
+            BallKind::Silver => "ICON_silver_ball",
+            BallKind::Pro => "ICON_pro_ball",
+        }
+    }
+
+    pub fn colour(&self) -> Color {
+        match self {
+            BallKind::Basic | BallKind::Big => Color::WHITE,
+            BallKind::Bomb => Color::srgb(1.4, 0.5, 0.2),
+            BallKind::Lightning => Color::srgb(1.2, 1.2, 0.4),
+            BallKind::Multi => Color::srgb(0.5, 1.3, 0.5),
+            BallKind::Gold => Color::srgb(1.5, 1.1, 0.3),
+            BallKind::Silver => Color::srgb(1.1, 1.1, 1.3),
+            BallKind::Pro => Color::srgb(1.1, 0.5, 1.4),
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        match self {
+            BallKind::Big => 10.0,
+            _ => 5.0,
+        }
+    }
+
+    pub fn damage_multiplier(&self) -> u16 {
+        match self {
+            BallKind::Big | BallKind::Pro => 2,
+            _ => 1,
+        }
+    }
+
+    /// Scales both the minimum and maximum speed.
+    pub fn speed_multiplier(&self) -> f32 {
+        match self {
+            BallKind::Big => 0.8,
+            BallKind::Pro => 1.25,
+            _ => 1.0,
+        }
+    }
+
+    /// How many of a broken block's resources the ball picks up.
+    pub fn resource_yield(&self, rng: &mut impl Rng) -> u32 {
+        match self {
+            BallKind::Gold => 2,
+            BallKind::Silver if rng.random_bool(SILVER_DOUBLE_CHANCE) => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Ball kinds the player has unlocked, and the one that gets fired next.
+#[derive(Resource, Debug)]
+pub struct Loadout {
+    kinds: Vec<BallKind>,
+    selected: usize,
+}
+
+impl Default for Loadout {
+    fn default() -> Self {
+        Self {
+            kinds: vec![BallKind::Basic],
+            selected: 0,
+        }
+    }
+}
+
+impl Loadout {
+    pub fn selected(&self) -> BallKind {
+        self.kinds[self.selected]
+    }
+
+    /// Unlock the first `count` kinds of [`BallKind::ALL`], a respec can lock some again.
+    pub fn unlock(&mut self, count: usize) {
+        self.kinds = BallKind::ALL[..count.clamp(1, BallKind::ALL.len())].to_vec();
+        if self.selected >= self.kinds.len() {
+            self.selected = 0;
+        }
+    }
+
+    pub fn next(&mut self) -> BallKind {
+        self.selected = (self.selected + 1) % self.kinds.len();
+        self.selected()
+    }
+}
+
+fn cycle_loadout(
+    query: Query<&ActionState<PaddleAction>, With<Paddle>>,
+    mut loadout: ResMut<Loadout>,
+    mut commands: Commands,
+) {
+    for action in &query {
+        if action.just_pressed(&PaddleAction::NextBall) {
+            let kind = loadout.next();
+            commands.trigger(UpdateStatsBarBallKindEvent { kind });
+        }
+    }
+}
+
+fn on_new_ball_upgrade(
+    trigger: Trigger<UpgradeLevelChangedEvent>,
+    shop_stats: Res<ShopStats>,
+    mut loadout: ResMut<Loadout>,
+    mut commands: Commands,
+) {
+    if trigger.event().item != ShopItem::Balls {
+        return;
+    }
+    loadout.unlock(shop_stats.ball_kinds());
+    commands.trigger(UpdateStatsBarBallKindEvent {
+        kind: loadout.selected(),
+    });
+}
+
+/// Triggered on a ball whenever it hits a block.
+#[derive(Event, Debug)]
+pub struct BallHitBlockEvent {
+    pub block: Entity,
+    pub position: BlockPosition,
+}
+
+fn on_ball_hit_block(
+    trigger: Trigger<BallHitBlockEvent>,
+    mut ball_query: Query<(&mut BallKind, &BallStats, &Transform, &Velocity), With<Ball>>,
+    block_query: Query<&Transform, With<Block>>,
+    grid: Res<BlockGrid>,
+    assets: Res<GameImageAssets>,
+    mut commands: Commands,
+) {
+    let ball = trigger.entity();
+    let event = trigger.event();
+    let Ok((mut kind, stats, transform, velocity)) = ball_query.get_mut(ball) else {
+        return;
+    };
+    let damage = stats.damage;
+
+    match *kind {
+        BallKind::Bomb => {
+            // the block that was hit already took the ball's own damage
+            let targets = grid
+                .around(event.position.column, event.position.row, BOMB_RADIUS)
+                .map(|(_, cell)| cell.entity)
+                .filter(|entity| *entity != event.block)
+                .collect();
+            commands.trigger(BlockDamageEvent {
+                blocks: targets,
+                damage,
+                ball,
+            });
+        }
+        BallKind::Lightning => {
+            let mut rng = rand::rng();
+            let targets: Vec<Entity> = grid
+                .around(event.position.column, event.position.row, LIGHTNING_RANGE)
+                .map(|(_, cell)| cell.entity)
+                .filter(|entity| *entity != event.block)
+                .choose_multiple(&mut rng, LIGHTNING_ARCS);
+            for target in block_query.iter_many(&targets) {
+                commands.trigger(BoxParticlesEvent {
+                    init_position: transform.translation.truncate(),
+                    target_position: target.translation.truncate(),
+                    z_index: 5.0,
+                    color: kind.colour(),
+                    target_color: kind.colour().with_alpha(0.0),
+                    size: Vec2::new(4., 4.),
+                    target_scale: Vec3::ONE,
+                    duration: Duration::from_millis(150),
+                });
+            }
+            commands.trigger(BlockDamageEvent {
+                blocks: targets,
+                damage,
+                ball,
+            });
+        }
+        BallKind::Multi => {
+            // split once, the original carries on as a basic ball
+            *kind = BallKind::Basic;
+            for angle in [-MULTI_SPREAD, MULTI_SPREAD] {
+                let linvel = velocity.linvel.rotate(Vec2::from_angle(angle.to_radians()));
+                // start clear of the original so they don't collide straight away
+                let offset = linvel.normalize_or_zero() * BallKind::Basic.radius() * 3.0;
+                let split = spawn_ball(
+                    &mut commands,
+                    Transform::from_translation(transform.translation + offset.extend(0.0)),
+                    &assets,
+                    BallKind::Basic,
+                    *stats,
+                );
+                commands.entity(split).insert(Velocity::linear(linvel));
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bevy_rapier2d::prelude::Collider;
+
+    use crate::{
+        ball::{BallLossRules, BallSpeedRules},
+        blocks::DespawnHack,
+        harness::{Harness, cell_centre},
+        shoppanel::PanelKind,
+    };
+
+    #[test]
+    fn next_ball_cycles_through_unlocked_kinds() {
+        let mut harness = Harness::new().with_resources(&[("light_blue", 40)]);
+        harness.tap(KeyCode::KeyQ);
+        assert_eq!(
+            harness.world().resource::<Loadout>().selected(),
+            BallKind::Basic
+        );
+
+        harness.spawn_shop_panel(PanelKind::Upgrade(ShopItem::Balls));
+        harness.tap(KeyCode::KeyE);
+        harness.tap(KeyCode::KeyQ);
+        harness.fire();
+
+        let ball = harness.balls()[0];
+        assert_eq!(harness.world().get::<BallKind>(ball), Some(&BallKind::Big));
+    }
+
+    #[test]
+    fn gold_ball_collects_double_resources() {
+        let mut harness = Harness::new();
+        harness.select_ball(BallKind::Gold);
+        let block = harness.spawn_block(20, 4, "light_blue");
+        harness.set_paddle_position(cell_centre(20, 0));
+
+        harness.fire();
+        let ball = harness.balls()[0];
+        for _ in 0..120 {
+            harness.step(1);
+            if harness.world().get::<DespawnHack>(block).is_some() {
+                break;
+            }
+        }
+        harness.step(1);
+        assert_eq!(harness.collected(ball, "light_blue"), 2);
+    }
+
+    /// Fire `kind` at a blue block at (20, 4) with light blue ones around it, and step until
+    /// the blue block takes its first hit.
+    fn hit_surrounded_block(kind: BallKind, neighbours: &[(usize, usize)]) -> (Harness, Entity) {
+        let mut harness = Harness::new();
+        harness.select_ball(kind);
+        let block = harness.spawn_block(20, 4, "blue");
+        for &(column, row) in neighbours {
+            harness.spawn_block(column, row, "light_blue");
+        }
+        let start = harness.hitpoints(block).unwrap();
+        harness.set_paddle_position(cell_centre(20, 0));
+
+        harness.fire();
+        for _ in 0..120 {
+            harness.step(1);
+            if harness.hitpoints(block).unwrap() < start {
+                break;
+            }
+        }
+        harness.step(1);
+        (harness, block)
+    }
+
+    fn light_blue_blocks(harness: &mut Harness) -> usize {
+        let light_blue = harness.block_type("light_blue");
+        harness
+            .world_mut()
+            .query_filtered::<&Block, Without<DespawnHack>>()
+            .iter(harness.app.world())
+            .filter(|block| block.0 == light_blue)
+            .count()
+    }
+
+    #[test]
+    fn bomb_ball_damages_the_blocks_around_the_one_it_hits_once() {
+        let (mut harness, block) =
+            hit_surrounded_block(BallKind::Bomb, &[(19, 4), (21, 4), (19, 5), (21, 5)]);
+
+        assert_eq!(light_blue_blocks(&mut harness), 0);
+        let damage = harness.world().resource::<ShopStats>().damage();
+        assert_eq!(harness.hitpoints(block), Some(20 - damage));
+    }
+
+    #[test]
+    fn lightning_ball_arcs_to_nearby_blocks() {
+        let (mut harness, block) = hit_surrounded_block(BallKind::Lightning, &[(18, 4), (22, 5)]);
+
+        assert_eq!(light_blue_blocks(&mut harness), 0);
+        let damage = harness.world().resource::<ShopStats>().damage();
+        assert_eq!(harness.hitpoints(block), Some(20 - damage));
+    }
+
+    #[test]
+    fn multi_ball_splits_into_three_on_its_first_hit() {
+        let (mut harness, _) = hit_surrounded_block(BallKind::Multi, &[]);
+
+        let balls = harness.balls();
+        assert_eq!(balls.len(), 3);
+        for ball in balls {
+            assert_eq!(
+                harness.world().get::<BallKind>(ball),
+                Some(&BallKind::Basic)
+            );
+        }
+    }
+
+    #[test]
+    fn big_ball_hits_harder_but_moves_slower() {
+        let mut harness = Harness::new();
+        harness.select_ball(BallKind::Big);
+        harness.fire();
+
+        let ball = harness.balls()[0];
+        let stats = *harness.world().get::<BallStats>(ball).unwrap();
+        let basic = BallStats::new(
+            harness.world().resource::<ShopStats>(),
+            harness.world().resource::<BallSpeedRules>(),
+            harness.world().resource::<BallLossRules>(),
+            BallKind::Basic,
+        );
+        assert_eq!(stats.damage, basic.damage * 2);
+        assert!(stats.max_speed < basic.max_speed);
+        assert!(stats.min_speed < basic.min_speed);
+    }
+
+    #[test]
+    fn pro_ball_hits_harder_and_moves_faster() {
+        let mut harness = Harness::new();
+        harness.select_ball(BallKind::Pro);
+        harness.fire();
+
+        let ball = harness.balls()[0];
+        let stats = *harness.world().get::<BallStats>(ball).unwrap();
+        let basic = BallStats::new(
+            harness.world().resource::<ShopStats>(),
+            harness.world().resource::<BallSpeedRules>(),
+            harness.world().resource::<BallLossRules>(),
+            BallKind::Basic,
+        );
+        assert_eq!(stats.damage, basic.damage * 2);
+        assert!(stats.min_speed > basic.min_speed);
+        assert!(harness.ball_velocity(ball).length() > basic.min_speed);
+    }
+
+    #[test]
+    fn big_ball_is_twice_the_size() {
+        let mut harness = Harness::new();
+        harness.select_ball(BallKind::Big);
+        harness.fire();
+
+        let ball = harness.balls()[0];
+        let radius = harness
+            .world()
+            .get::<Collider>(ball)
+            .and_then(|collider| collider.as_ball().map(|ball| ball.radius()))
+            .unwrap();
+        assert_eq!(radius, BallKind::Basic.radius() * 2.0);
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    app_state::AppState,
+    ball::{Ball, PreviousVelocity},
+    paddle::{Paddle, PaddleAction},
+    statsbar::UpdateStatsBarMagnetEvent,
+};
+
+pub struct MagnetPlugin;
+
+impl Plugin for MagnetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, pull_balls.run_if(in_state(AppState::Game)))
+            .add_systems(Update, draw_magnet_field.run_if(in_state(AppState::Game)));
+    }
+}
+
+/// How far from the paddle balls get pulled.
+const MAGNET_RADIUS: f32 = 250.0;
+/// Acceleration towards the paddle right next to it, fading to nothing at the radius.
+const MAGNET_STRENGTH: f32 = 1200.0;
+/// Energy used per second of pulling, a full meter lasts two seconds.
+const MAGNET_DRAIN: f32 = 0.5;
+/// Energy regained per second while the magnet is off.
+const MAGNET_RECHARGE: f32 = 0.2;
+/// Seconds the magnet is locked out after the meter runs dry.
+const MAGNET_COOLDOWN: f32 = 3.0;
+const MAGNET_COLOUR: Color = Color::srgba(0.4, 0.8, 1.0, 0.5);
+
+/// Paddle tractor beam that pulls nearby balls in while the Magnet action is held.
+#[derive(Component, Debug)]
+pub struct Magnet {
+    /// Fraction of the meter left, from 0 to 1.
+    pub energy: f32,
+    /// Seconds until the magnet can be used again.
+    pub cooldown: f32,
+    pub active: bool,
+}
+
+impl Default for Magnet {
+    fn default() -> Self {
+        Self {
+            energy: 1.0,
+            cooldown: 0.0,
+            active: false,
+        }
+    }
+}
+
+fn pull_balls(
+    mut paddle_query: Query<(&ActionState<PaddleAction>, &Transform, &mut Magnet), With<Paddle>>,
+    mut ball_query: Query<(&Transform, &mut Velocity, &mut PreviousVelocity), With<Ball>>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let Ok((action_state, transform, mut magnet)) = paddle_query.get_single_mut() else {
+        return;
+    };
+    let delta = time.delta_secs();
+    let energy = magnet.energy;
+
+    magnet.cooldown = (magnet.cooldown - delta).max(0.0);
+    magnet.active = action_state.pressed(&PaddleAction::Magnet)
+        && magnet.cooldown == 0.0
+        && magnet.energy > 0.0;
+    if !magnet.active {
+        magnet.energy = (magnet.energy + MAGNET_RECHARGE * delta).min(1.0);
+    } else {
+        magnet.energy = (magnet.energy - MAGNET_DRAIN * delta).max(0.0);
+        if magnet.energy == 0.0 {
+            magnet.cooldown = MAGNET_COOLDOWN;
+        }
+
+        let paddle = transform.translation.truncate();
+        for (ball_transform, mut velocity, mut previous_velocity) in ball_query.iter_mut() {
+            let offset = paddle - ball_transform.translation.truncate();
+            let distance = offset.length();
+            if distance > MAGNET_RADIUS || distance == 0.0 {
+                continue;
+            }
+            // pulls harder the closer the ball gets
+            let pull = MAGNET_STRENGTH * (1.0 - distance / MAGNET_RADIUS);
+            velocity.linvel += offset / distance * pull * delta;
+            // update both so override_physics doesn't undo the pull like a bounce
+            previous_velocity.linvel = velocity.linvel;
+        }
+    }
+
+    if magnet.energy != energy {
+        commands.trigger(UpdateStatsBarMagnetEvent {
+            energy: magnet.energy,
+            ready: magnet.cooldown == 0.0,
+        });
+    }
+}
+
+fn draw_magnet_field(paddle_query: Query<(&Transform, &Magnet), With<Paddle>>, mut gizmos: Gizmos) {
+    let Ok((transform, magnet)) = paddle_query.get_single() else {
+        return;
+    };
+    if magnet.active {
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            MAGNET_RADIUS * magnet.energy.max(0.1),
+            MAGNET_COLOUR,
+        );
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            MAGNET_RADIUS,
+            MAGNET_COLOUR,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use leafwing_input_manager::prelude::Buttonlike;
+
+    use crate::{ball::CollectedResources, harness::Harness};
+
+    #[test]
+    fn magnet_pulls_balls_back_until_it_runs_dry() {
+        let mut harness = Harness::new();
+        let paddle = harness.paddle();
+        let purple = harness.block_type("purple");
+        harness.fire();
+        let ball = harness.balls()[0];
+        harness
+            .world_mut()
+            .get_mut::<CollectedResources>(ball)
+            .unwrap()
+            .add_many(purple, 5);
+        harness.step(30);
+        assert!(harness.ball_velocity(ball).y < 0.0);
+
+        KeyCode::KeyR.press(harness.world_mut());
+        harness.step(60);
+        assert_eq!(harness.collected(paddle, "purple"), 5);
+
+        harness.step(120);
+        let magnet = harness.world().get::<Magnet>(paddle).unwrap();
+        assert!(magnet.energy < 0.5);
+        assert!(!magnet.active);
+        assert!(magnet.cooldown > 0.0);
+    }
+}
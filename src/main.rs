@@ -1,7 +1,9 @@
+use aim::AimPlugin;
 use app_state::AppState;
 use asset_loading::{AssetLoadingPlugin, GameImageAssets};
 use audio::InternalAudioPlugin;
 use ball::BallPlugin;
+use behaviours::BlockBehavioursPlugin;
 use bevy::{
     asset::AssetMetaCheck,
     core_pipeline::{
@@ -15,27 +17,54 @@ use bevy::{
     window::{PrimaryWindow, WindowResized, WindowResolution},
 };
 use bevy_dev_tools::fps_overlay::{FpsOverlayConfig, FpsOverlayPlugin};
+use bevy_hui::prelude::HuiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_tweening::TweeningPlugin;
-use blocks::{BLOCK_GROUP_OFFSET, BlocksPlugin, WALL_WIDTH};
+use blocks::{BLOCK_GROUP_OFFSET, BlockRegistry, BlocksPlugin, WALL_WIDTH};
+use chunks::ChunksPlugin;
+use dash::DashPlugin;
+use loadout::LoadoutPlugin;
+use magnet::MagnetPlugin;
+use outposts::OutpostsPlugin;
 use paddle::PaddlePlugin;
 use particles::ParticlesPlugin;
 use physics::PhysicsPlugin;
+use pickups::PickupsPlugin;
+use refinery::RefineryPlugin;
+use seed::SeedPlugin;
 use shop::ShopPlugin;
+use shopmenu::ShopMenuPlugin;
 use shoppanel::ShopPanelPlugin;
 use statsbar::{STATS_BAR_HEIGHT, StatsBarPlugin};
+use strata::{StrataPlugin, StratumEnteredEvent};
 
+mod aim;
 mod app_state;
 mod asset_loading;
 mod audio;
 mod ball;
+mod behaviours;
 mod blocks;
+mod chunks;
+mod dash;
+#[cfg(test)]
+mod harness;
+mod loadout;
+mod magnet;
+mod outposts;
 mod paddle;
 mod particles;
 mod physics;
+mod pickups;
+mod refinery;
+mod rooms;
+mod seed;
 mod shop;
+mod shopmenu;
 mod shoppanel;
 mod statsbar;
+mod strata;
+mod worldgen;
 
 fn main() {
     App::new()
@@ -90,18 +119,32 @@ fn main() {
         // .add_plugins(WorldInspectorPlugin::new())
         .add_plugins(AssetLoadingPlugin)
         .add_plugins(TweeningPlugin)
+        .add_plugins(HuiPlugin)
         .add_plugins(ParticlesPlugin)
+        .add_plugins(SeedPlugin)
         .add_plugins(BlocksPlugin)
+        .add_plugins(ChunksPlugin)
+        .add_plugins(BlockBehavioursPlugin)
+        .add_plugins(StrataPlugin)
         .add_plugins(PaddlePlugin)
+        .add_plugins(AimPlugin)
+        .add_plugins(MagnetPlugin)
+        .add_plugins(DashPlugin)
         .add_plugins(BallPlugin)
+        .add_plugins(LoadoutPlugin)
+        .add_plugins(PickupsPlugin)
         .add_plugins(PhysicsPlugin)
         .add_plugins(StatsBarPlugin)
         .add_plugins(ShopPlugin)
         .add_plugins(ShopPanelPlugin)
+        .add_plugins(ShopMenuPlugin)
+        .add_plugins(RefineryPlugin)
+        .add_plugins(OutpostsPlugin)
         .add_plugins(InternalAudioPlugin)
         .add_systems(Startup, setup_camera)
         .add_systems(OnEnter(AppState::Game), spawn_background)
         .add_systems(Update, on_resize_system)
+        .add_observer(swap_background)
         .init_state::<AppState>()
         .enable_state_scoped_entities::<AppState>()
         .run();
@@ -149,6 +192,7 @@ pub struct BackgroundHoriWall;
 fn spawn_background(
     mut commands: Commands,
     assets: Res<GameImageAssets>,
+    registry: Res<BlockRegistry>,
     camera_query: Query<(Entity, &Camera, &GlobalTransform)>,
 ) {
     let camera = camera_query
@@ -158,7 +202,7 @@ fn spawn_background(
         .spawn((
             Background,
             Sprite {
-                image: assets.background.clone(),
+                image: background_image(&assets, &registry.strata()[0].background),
                 custom_size: camera.1.logical_viewport_size(),
                 ..Default::default()
             },
@@ -209,6 +253,25 @@ fn spawn_background(
     }
 }
 
+fn background_image(assets: &GameImageAssets, stem: &str) -> Handle<Image> {
+    assets.backgrounds.get(stem).cloned().unwrap_or_else(|| {
+        warn!("Missing background '{}'", stem);
+        Handle::default()
+    })
+}
+
+fn swap_background(
+    trigger: Trigger<StratumEnteredEvent>,
+    mut bg_query: Query<&mut Sprite, With<Background>>,
+    assets: Res<GameImageAssets>,
+    registry: Res<BlockRegistry>,
+) {
+    let image = background_image(&assets, &registry.strata()[trigger.stratum].background);
+    for mut sprite in bg_query.iter_mut() {
+        sprite.image = image.clone();
+    }
+}
+
 fn on_resize_system(
     mut bg_query: Query<&mut Sprite, With<Background>>,
     mut bgvertwall_query: Query<&mut Sprite, (With<BackgroundVertWall>, Without<Background>)>,
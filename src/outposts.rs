@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use rand::{SeedableRng, rngs::StdRng, seq::IndexedRandom};
+
+use crate::{
+    app_state::AppState,
+    asset_loading::GameImageAssets,
+    blocks::{BLOCK_GROUP_OFFSET, BLOCK_SIZE, BlockRegistry, init_world_generator},
+    seed::Seed,
+    shop::{ShopItem, ShopStats},
+    shoppanel::{spawn_refresh_panel, spawn_shop_panel},
+    worldgen::WorldGenerator,
+};
+
+pub struct OutpostsPlugin;
+
+impl Plugin for OutpostsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::Game),
+            spawn_outposts.after(init_world_generator),
+        );
+    }
+}
+
+/// Gap between the centres of neighbouring panels in an outpost.
+const OUTPOST_PANEL_SPACING: f32 = 120.0;
+
+/// Upgrades sold at the `index`th outpost, picked from the seed so a world always has the same.
+fn outpost_items(stats: &ShopStats, seed: Seed, index: usize, count: usize) -> Vec<ShopItem> {
+    let items: Vec<ShopItem> = stats.items().collect();
+    let mut rng = StdRng::seed_from_u64(((seed.0 as u64) << 32) | index as u64);
+    items.choose_multiple(&mut rng, count).copied().collect()
+}
+
+fn spawn_outposts(
+    mut commands: Commands,
+    generator: Res<WorldGenerator>,
+    assets: Res<GameImageAssets>,
+    stats: Res<ShopStats>,
+    seed: Res<Seed>,
+    registry: Res<BlockRegistry>,
+) {
+    let Some((width, height)) = generator.outpost_size() else {
+        return;
+    };
+    let count = registry.outposts().map_or(0, |outposts| outposts.upgrades);
+
+    for (index, &(top, left)) in generator.outposts().iter().enumerate() {
+        let centre = Vec2::new(
+            -BLOCK_GROUP_OFFSET + BLOCK_SIZE * (left as f32 + width as f32 / 2.0),
+            BLOCK_SIZE - BLOCK_SIZE * (top as f32 + height as f32 / 2.0),
+        );
+        let items = outpost_items(&stats, *seed, index, count);
+
+        // upgrades in a row with the refresh at the end, centred in the room
+        let panels = items.len() + 1;
+        let offset =
+            |i: usize| Vec2::X * OUTPOST_PANEL_SPACING * (i as f32 - (panels - 1) as f32 / 2.0);
+        for (i, &item) in items.iter().enumerate() {
+            spawn_shop_panel(
+                &mut commands,
+                &assets,
+                item,
+                stats.name(item),
+                centre + offset(i),
+            );
+        }
+        spawn_refresh_panel(&mut commands, &assets, centre + offset(items.len()));
+        debug!("Outpost at row {} selling {:?}", top, items);
+    }
+}
@@ -4,21 +4,25 @@ use bevy::{math::VectorSpace, prelude::*};
 use bevy_rapier2d::prelude::{
     ActiveCollisionTypes, ActiveEvents, Ccd, Collider, ColliderMassProperties, CollisionEvent,
     CollisionGroups, ExternalImpulse, Friction, GravityScale, KinematicCharacterController,
-    LockedAxes, Restitution, RigidBody, Sensor, Velocity,
+    LockedAxes, ReadMassProperties, Restitution, RigidBody, Sensor, Velocity,
 };
 use leafwing_input_manager::{input_map, prelude::*};
 use rand::Rng;
 
 use crate::{
+    aim::Aim,
     app_state::AppState,
     asset_loading::GameImageAssets,
-    ball::{CollectedResources, spawn_ball},
+    ball::{BallLossRules, BallSpeedRules, BallStats, CollectedResources, spawn_ball},
     blocks::{BLOCK_GROUP_OFFSET, BLOCK_SIZE},
+    dash::Dash,
+    loadout::Loadout,
+    magnet::Magnet,
     particles::{BoxParticle, BoxParticlesEvent},
     physics::{BALL_GROUP, BLOCK_GROUP, PADDLE_GROUP, PADDLE_SHOP_GROUP, WALL_GROUP},
-    shop::{ShopItem, ShopStats, try_buy},
-    shoppanel::{ShopPanel, UpdateShopPanelsEvent},
-    statsbar::{UpdateStatsBarBallsEvent, UpdateStatsBarDepthEvent, UpdateStatsBarResourcesEvent},
+    shop::{ShopItem, ShopStats, UpgradeLevelChangedEvent, purchase, respec},
+    shoppanel::{PanelKind, ShopPanel},
+    statsbar::{UpdateStatsBarBallsEvent, UpdateStatsBarDepthEvent},
 };
 
 pub struct PaddlePlugin;
@@ -27,19 +31,48 @@ pub struct PaddlePlugin;
 pub enum PaddleAction {
     #[actionlike(DualAxis)]
     Move,
+    #[actionlike(DualAxis)]
+    Aim,
     Fire,
     Interact,
+    NextBall,
+    ToggleBurst,
+    ToggleTilt,
+    /// Go back to firing along the tilt after aiming with the mouse or stick.
+    ToggleAim,
+    Magnet,
+    Dash,
+    #[actionlike(Axis)]
+    Rotate,
+    Shop,
 }
 
 impl PaddleAction {
     pub fn default_bindings() -> InputMap<Self> {
         let mut input_map = InputMap::default();
 
-        // kbm controls only for now
+        // kbm controls
         input_map.insert_dual_axis(Self::Move, VirtualDPad::arrow_keys());
         input_map.insert_dual_axis(Self::Move, VirtualDPad::wasd());
         input_map.insert(Self::Fire, KeyCode::Space);
         input_map.insert(Self::Interact, KeyCode::KeyE);
+        input_map.insert(Self::NextBall, KeyCode::KeyQ);
+        input_map.insert(Self::ToggleBurst, KeyCode::KeyF);
+        input_map.insert(Self::Magnet, KeyCode::KeyR);
+        input_map.insert(Self::Magnet, MouseButton::Right);
+        input_map.insert(Self::Dash, KeyCode::ShiftLeft);
+        input_map.insert_axis(Self::Rotate, VirtualAxis::new(KeyCode::KeyZ, KeyCode::KeyC));
+        input_map.insert(Self::ToggleTilt, KeyCode::KeyX);
+        input_map.insert(Self::ToggleAim, KeyCode::KeyV);
+        input_map.insert(Self::Shop, KeyCode::Tab);
+        // enough of a pad layout to move, buy and get around the shop menu
+        input_map.insert_dual_axis(Self::Move, GamepadStick::LEFT);
+        input_map.insert_dual_axis(Self::Move, VirtualDPad::dpad());
+        input_map.insert(Self::Interact, GamepadButton::South);
+        input_map.insert(Self::Shop, GamepadButton::Start);
+        // the mouse aims too, see aim::update_aim
+        input_map.insert_dual_axis(Self::Aim, GamepadStick::RIGHT);
+        input_map.insert(Self::ToggleAim, GamepadButton::RightThumb);
 
         input_map
     }
@@ -49,9 +82,19 @@ impl Plugin for PaddlePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(InputManagerPlugin::<PaddleAction>::default())
             .add_systems(OnEnter(AppState::Game), spawn_paddle)
-            .add_systems(FixedUpdate, move_paddle.run_if(in_state(AppState::Game)))
+            .add_systems(
+                FixedUpdate,
+                (move_paddle, fire_balls)
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
+            )
             .add_systems(PostUpdate, follow_cam.run_if(in_state(AppState::Game)))
-            .add_systems(Update, spawn_particles.run_if(in_state(AppState::Game)));
+            .add_systems(
+                Update,
+                (spawn_particles, tint_charging_paddle).run_if(in_state(AppState::Game)),
+            )
+            .add_observer(resize_paddle)
+            .add_observer(reset_tilt);
     }
 }
 
@@ -64,13 +107,45 @@ pub struct NumBalls(pub u32);
 #[derive(Component)]
 pub struct PaddleBottomSprite;
 
+/// How far a charge shot has been charged, from 0 to 1.
+#[derive(Component, Default, PartialEq)]
+pub struct Charge(pub f32);
+
+/// Whether Fire launches one ball or a burst of them, once burst fire is unlocked.
+#[derive(Component, Default, Debug, PartialEq, Eq)]
+pub enum FireMode {
+    #[default]
+    Single,
+    Burst,
+}
+
+/// Whether the paddle tilts with its movement or is angled by the Rotate action.
+#[derive(Component, Default, Debug, PartialEq, Eq)]
+pub enum TiltMode {
+    #[default]
+    Auto,
+    /// Switched to by rotating once manual rotation is unlocked, ToggleTilt switches back.
+    Manual,
+}
+
 // TODO height won't change, but width will so need to move to a resource
 const PADDLE_WIDTH: f32 = 32.2;
 const PADDLE_HEIGHT: f32 = 5.0;
 const UFO_SCALE: f32 = PADDLE_HEIGHT / 60. * 2.;
 
+/// How far below the paddle's centre balls are launched from, so they start clear of it.
+pub const BALL_LAUNCH_OFFSET: f32 = PADDLE_HEIGHT + 1.0;
+
 const PADDLE_MAX_HEIGHT: f32 = 1000.0;
+/// Radians per second the paddle turns under manual rotation.
+const ROTATE_SPEED: f32 = 1.5;
 const PADDLE_BLOOM: f32 = 1.4;
+const PADDLE_CHARGED_COLOUR: Color = Color::srgb(2.5, 1.2, 0.3);
+
+/// Seconds of holding Fire to fully charge a shot.
+const CHARGE_TIME: f32 = 1.5;
+/// Angle between balls in a burst.
+const BURST_SPREAD: f32 = 8.0;
 
 fn spawn_paddle(mut commands: Commands, assets: Res<GameImageAssets>) {
     commands
@@ -91,12 +166,14 @@ fn spawn_paddle(mut commands: Commands, assets: Res<GameImageAssets>) {
                 GravityScale(0.0),
                 ColliderMassProperties::Density(10.0),
                 Friction::coefficient(1.0),
-                Restitution::coefficient(2.0),
+                Restitution::coefficient(1.0),
                 ActiveCollisionTypes::all(),
                 ActiveEvents::COLLISION_EVENTS,
                 Ccd::enabled(),
                 CollisionGroups::new(PADDLE_GROUP, WALL_GROUP | BALL_GROUP | BLOCK_GROUP),
                 Velocity::default(),
+                ExternalImpulse::default(),
+                ReadMassProperties::default(),
             ),
             StateScoped(AppState::Game),
             Name::new("Paddle"),
@@ -105,6 +182,14 @@ fn spawn_paddle(mut commands: Commands, assets: Res<GameImageAssets>) {
             InheritedVisibility::default(),
             CollectedResources::new(),
             NumBalls(3),
+            (
+                Aim::default(),
+                Charge::default(),
+                FireMode::default(),
+                Magnet::default(),
+                Dash::default(),
+                TiltMode::default(),
+            ),
         ))
         .with_children(|parent| {
             parent.spawn(Sprite {
@@ -192,12 +277,13 @@ fn spawn_paddle(mut commands: Commands, assets: Res<GameImageAssets>) {
 fn move_paddle(
     mut query: Query<
         (
-            Entity,
             &ActionState<PaddleAction>,
             &mut Transform,
             &mut Velocity,
             &mut NumBalls,
             &mut CollectedResources,
+            &Dash,
+            &mut TiltMode,
         ),
         With<Paddle>,
     >,
@@ -205,17 +291,17 @@ fn move_paddle(
     mut commands: Commands,
     assets: Res<GameImageAssets>,
     mut shop_panel_query: Query<(&ShopPanel)>,
-    mut paddle_bottom_query: Query<(&PaddleBottomSprite, &mut Sprite)>,
     camera_query: Query<(Entity, &OrthographicProjection), With<Camera>>,
     mut stats: ResMut<ShopStats>,
 ) {
     let (
-        paddle_entity,
         action_state,
         mut transform,
         mut vel,
         mut num_balls,
         mut collected_resources,
+        dash,
+        mut tilt_mode,
     ) = query.get_single_mut().expect("Failed to get paddle entity");
     let (_, orthoproj) = camera_query.get_single().expect("Need single camera.");
     let half_screen_size = orthoproj.area.height() / 2.0;
@@ -228,11 +314,34 @@ fn move_paddle(
         target_lin_vel = action_state.clamped_axis_pair(&PaddleAction::Move) * stats.speed();
         lin_damping *= 2.0;
     }
-    vel.linvel = vel.linvel.lerp(target_lin_vel, lin_damping);
+    // let a dash carry the paddle until it's over
+    if !dash.active() {
+        vel.linvel = vel.linvel.lerp(target_lin_vel, lin_damping);
+    }
 
     // handle rotation
     const MAX_ROTATION: f32 = f32::to_radians(5.0);
-    if transform.rotation.z.abs() > MAX_ROTATION {
+    let rotate = action_state.value(&PaddleAction::Rotate);
+    if stats.max_rotation() > 0.0 {
+        if action_state.just_pressed(&PaddleAction::ToggleTilt) {
+            *tilt_mode = match *tilt_mode {
+                TiltMode::Auto => TiltMode::Manual,
+                TiltMode::Manual => TiltMode::Auto,
+            };
+        } else if rotate != 0.0 {
+            *tilt_mode = TiltMode::Manual;
+        }
+    }
+    if *tilt_mode == TiltMode::Manual {
+        // positive turns clockwise, and the paddle holds its angle when let go
+        let max_rotation = stats.max_rotation().to_radians();
+        let angle = transform.rotation.to_euler(EulerRot::XYZ).2;
+        vel.angvel = -rotate * ROTATE_SPEED;
+        if angle.abs() >= max_rotation && angle.signum() == vel.angvel.signum() {
+            vel.angvel = 0.0;
+            transform.rotation = Quat::from_rotation_z(angle.clamp(-max_rotation, max_rotation));
+        }
+    } else if transform.rotation.z.abs() > MAX_ROTATION {
         // past the limit, stop rotating
         vel.angvel = 0.0;
         transform.rotation.z = transform.rotation.z.signum() * MAX_ROTATION;
@@ -252,12 +361,6 @@ fn move_paddle(
         vel.angvel = FloatExt::lerp(vel.angvel, target_ang_vel, ang_damping);
     }
 
-    if action_state.just_pressed(&PaddleAction::Fire) && num_balls.0 > 0 {
-        num_balls.0 -= 1;
-        commands.trigger(UpdateStatsBarBallsEvent { balls: num_balls.0 });
-        spawn_ball(&mut commands, transform.clone(), assets);
-    }
-
     if action_state.just_pressed(&PaddleAction::Interact) {
         // find enabled shop panel
         for shop_panel in shop_panel_query.iter() {
@@ -265,79 +368,202 @@ fn move_paddle(
                 continue;
             }
 
-            if shop_panel.is_refresh {
-                let init_balls = num_balls.0;
-                // refresh ball count
-                num_balls.0 = stats.capacity() as u32;
-                commands.trigger(UpdateStatsBarBallsEvent { balls: num_balls.0 });
-
-                // particles
-                let mut rng = rand::rng();
-                for _ in init_balls..num_balls.0 {
-                    commands.trigger(BoxParticlesEvent {
-                        init_position: transform.translation.truncate()
-                            + Vec2::new(
-                                rng.random_range(-20.0..20.0),
-                                rng.random_range(-20.0..20.0),
-                            ),
-                        target_position: Vec2::new(
-                            -BLOCK_GROUP_OFFSET,
-                            transform.translation.y + half_screen_size + 10.0,
-                        ),
-                        z_index: -5.0,
-                        color: Color::WHITE,
-                        target_color: Color::WHITE,
-                        size: Vec2::new(10., 10.),
-                        target_scale: Vec3::ONE * 1.2,
-                        duration: Duration::from_millis(500),
-                    });
-                }
+            let item = match shop_panel.kind {
+                PanelKind::Upgrade(item) => item,
+                PanelKind::Refresh => {
+                    let init_balls = num_balls.0;
+                    // refresh ball count
+                    num_balls.0 = stats.capacity() as u32;
+                    commands.trigger(UpdateStatsBarBallsEvent { balls: num_balls.0 });
 
-                break;
-            }
+                    // particles
+                    let mut rng = rand::rng();
+                    for _ in init_balls..num_balls.0 {
+                        commands.trigger(BoxParticlesEvent {
+                            init_position: transform.translation.truncate()
+                                + Vec2::new(
+                                    rng.random_range(-20.0..20.0),
+                                    rng.random_range(-20.0..20.0),
+                                ),
+                            target_position: Vec2::new(
+                                -BLOCK_GROUP_OFFSET,
+                                transform.translation.y + half_screen_size + 10.0,
+                            ),
+                            z_index: -5.0,
+                            color: Color::WHITE,
+                            target_color: Color::WHITE,
+                            size: Vec2::new(10., 10.),
+                            target_scale: Vec3::ONE * 1.2,
+                            duration: Duration::from_millis(500),
+                        });
+                    }
 
-            // try to buy
-            if let Some(cost) = match shop_panel.item {
-                ShopItem::Damage => stats.damage_cost(),
-                ShopItem::Speed => stats.speed_cost(),
-                ShopItem::Capacity => stats.capacity_cost(),
-                ShopItem::Size => stats.size_cost(),
-            } {
-                if try_buy(&cost, &mut collected_resources.counts) {
-                    match shop_panel.item {
-                        ShopItem::Damage => stats.damage_level += 1,
-                        ShopItem::Speed => stats.speed_level += 1,
-                        ShopItem::Capacity => stats.capacity_level += 1,
-                        ShopItem::Size => {
-                            stats.size_level += 1;
-                            // update paddle size
-                            for (_, mut sprite) in paddle_bottom_query.iter_mut() {
-                                // let vec = Vec2 { x: 193., y: 60. } * UFO_SCALE
-                                //     + Vec2::new(stats.size(), 0.0);
-                                sprite.custom_size =
-                                    Some(Vec2::new(stats.size(), 60.0 * UFO_SCALE));
-                            }
-                            // if let Some(mut cuboid) = collider.as_cuboid_mut() {
-                            //     cuboid.set_half_extents(Vec2::new(
-                            //         stats.size() / 2.,
-                            //         PADDLE_HEIGHT / 2.,
-                            //     ));
-                            // } else {
-                            //     info!("Failed to get cuboid from collider: {:?}", collider);
-                            // }
-                            commands.entity(paddle_entity).remove::<Collider>();
-                            commands
-                                .entity(paddle_entity)
-                                .insert(Collider::cuboid(stats.size() / 2., PADDLE_HEIGHT));
-                        }
+                    break;
+                }
+                PanelKind::Respec => {
+                    if respec(&mut stats, &mut collected_resources.counts, &mut commands) {
+                        // a smaller capacity can't hold the balls left over
+                        num_balls.0 = num_balls.0.min(stats.capacity());
+                        commands.trigger(UpdateStatsBarBallsEvent { balls: num_balls.0 });
                     }
-                    commands.trigger(UpdateStatsBarResourcesEvent);
-                    commands.trigger(UpdateShopPanelsEvent);
-                } else {
-                    info!("Failed to buy: {:?}", shop_panel.item);
-                    // todo play error sound
+                    break;
                 }
-            }
+            };
+
+            purchase(
+                item,
+                &mut stats,
+                &mut collected_resources.counts,
+                &mut commands,
+            );
+        }
+    }
+}
+
+fn resize_paddle(
+    trigger: Trigger<UpgradeLevelChangedEvent>,
+    paddle_query: Query<Entity, With<Paddle>>,
+    mut paddle_bottom_query: Query<&mut Sprite, With<PaddleBottomSprite>>,
+    stats: Res<ShopStats>,
+    mut commands: Commands,
+) {
+    if trigger.item != ShopItem::Size {
+        return;
+    }
+    for mut sprite in paddle_bottom_query.iter_mut() {
+        // let vec = Vec2 { x: 193., y: 60. } * UFO_SCALE
+        //     + Vec2::new(stats.size(), 0.0);
+        sprite.custom_size = Some(Vec2::new(stats.size(), 60.0 * UFO_SCALE));
+    }
+    for paddle_entity in paddle_query.iter() {
+        commands.entity(paddle_entity).remove::<Collider>();
+        commands
+            .entity(paddle_entity)
+            .insert(Collider::cuboid(stats.size() / 2., PADDLE_HEIGHT));
+    }
+}
+
+/// Hand the tilt back to the movement once manual rotation is locked again by a respec.
+fn reset_tilt(
+    trigger: Trigger<UpgradeLevelChangedEvent>,
+    mut paddle_query: Query<(&mut TiltMode, &mut Transform, &mut Velocity), With<Paddle>>,
+    stats: Res<ShopStats>,
+) {
+    if trigger.item != ShopItem::Rotate || stats.level(ShopItem::Rotate) > 1 {
+        return;
+    }
+    for (mut tilt_mode, mut transform, mut vel) in paddle_query.iter_mut() {
+        *tilt_mode = TiltMode::Auto;
+        transform.rotation = Quat::IDENTITY;
+        vel.angvel = 0.0;
+    }
+}
+
+/// Warm the underside of the paddle up as a charge shot builds.
+fn tint_charging_paddle(
+    query: Query<&Charge, (With<Paddle>, Changed<Charge>)>,
+    mut paddle_bottom_query: Query<&mut Sprite, With<PaddleBottomSprite>>,
+) {
+    let Ok(charge) = query.get_single() else {
+        return;
+    };
+    for mut sprite in paddle_bottom_query.iter_mut() {
+        sprite.color = Color::srgb(PADDLE_BLOOM, PADDLE_BLOOM, PADDLE_BLOOM)
+            .mix(&PADDLE_CHARGED_COLOUR, charge.0);
+    }
+}
+
+type FireQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static ActionState<PaddleAction>,
+        &'static Transform,
+        &'static mut NumBalls,
+        &'static Aim,
+        &'static mut Charge,
+        &'static mut FireMode,
+    ),
+    With<Paddle>,
+>;
+
+fn fire_balls(
+    mut query: FireQuery,
+    time: Res<Time>,
+    mut commands: Commands,
+    assets: Res<GameImageAssets>,
+    stats: Res<ShopStats>,
+    loadout: Res<Loadout>,
+    speed_rules: Res<BallSpeedRules>,
+    loss_rules: Res<BallLossRules>,
+) {
+    let (action_state, transform, mut num_balls, aim, mut charge, mut fire_mode) =
+        query.get_single_mut().expect("Failed to get paddle entity");
+
+    if action_state.just_pressed(&PaddleAction::ToggleBurst) && stats.burst() > 1 {
+        *fire_mode = match *fire_mode {
+            FireMode::Single => FireMode::Burst,
+            FireMode::Burst => FireMode::Single,
+        };
+        debug!("Fire mode: {:?}", *fire_mode);
+    }
+
+    // charge shots fire on release, plain shots as soon as Fire is pressed
+    let charged = if stats.max_charge() > 1.0 {
+        if action_state.pressed(&PaddleAction::Fire) && num_balls.0 > 0 {
+            let charged = (charge.0 + time.delta_secs() / CHARGE_TIME).min(1.0);
+            charge.set_if_neq(Charge(charged));
+        }
+        if !action_state.just_released(&PaddleAction::Fire) {
+            return;
+        }
+        std::mem::take(&mut charge.0)
+    } else {
+        if !action_state.just_pressed(&PaddleAction::Fire) {
+            return;
+        }
+        0.0
+    };
+    if num_balls.0 == 0 {
+        return;
+    }
+
+    let count = match *fire_mode {
+        FireMode::Single => 1,
+        FireMode::Burst => stats.burst().min(num_balls.0),
+    };
+    num_balls.0 -= count;
+    commands.trigger(UpdateStatsBarBallsEvent { balls: num_balls.0 });
+
+    let kind = loadout.selected();
+    let rotation = if aim.manual {
+        aim.rotation()
+    } else {
+        // unaimed shots go out along the tilt with a little jitter
+        let jitter = rand::rng().random_range(-5.0_f32.to_radians()..5.0_f32.to_radians());
+        transform.rotation * Quat::from_rotation_z(jitter)
+    };
+    let power = 1.0 + charged * (stats.max_charge() - 1.0);
+    let mut ball_stats = BallStats::new(&stats, &speed_rules, &loss_rules, kind);
+    ball_stats.damage = (ball_stats.damage as f32 * power).round() as u16;
+
+    for i in 0..count {
+        // fan the burst out around the aim, side by side so they don't collide
+        let offset = i as f32 - (count - 1) as f32 / 2.0;
+        let rotation = rotation * Quat::from_rotation_z((offset * BURST_SPREAD).to_radians());
+        let launch = transform.translation
+            + Vec3::new(offset * kind.radius() * 2.5, -BALL_LAUNCH_OFFSET, 0.0);
+        let ball = spawn_ball(
+            &mut commands,
+            transform.with_translation(launch).with_rotation(rotation),
+            &assets,
+            kind,
+            ball_stats,
+        );
+        if power > 1.0 {
+            commands.entity(ball).insert(Velocity::linear(
+                (rotation * Vec3::NEG_Y).truncate() * ball_stats.min_speed * power,
+            ));
         }
     }
 }
@@ -387,3 +613,110 @@ fn spawn_particles(
         depth: ((transform.translation.y - BLOCK_SIZE) / BLOCK_SIZE).floor() as i32,
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{harness::Harness, loadout::BallKind};
+
+    #[test]
+    fn burst_fire_launches_a_spread_of_balls() {
+        let mut harness = Harness::new();
+        harness
+            .world_mut()
+            .resource_mut::<ShopStats>()
+            .set_level(ShopItem::Burst, 3);
+
+        harness.tap(KeyCode::KeyF);
+        harness.fire();
+        let balls = harness.balls();
+        assert_eq!(balls.len(), 3);
+        let paddle = harness.paddle();
+        assert_eq!(harness.world().get::<NumBalls>(paddle).unwrap().0, 0);
+        let first = harness.ball_velocity(balls[0]);
+        assert!(
+            balls[1..]
+                .iter()
+                .all(|ball| harness.ball_velocity(*ball).angle_to(first).abs() > 0.01)
+        );
+    }
+
+    #[test]
+    fn charged_shot_fires_on_release_and_hits_harder() {
+        let mut harness = Harness::new();
+        harness
+            .world_mut()
+            .resource_mut::<ShopStats>()
+            .set_level(ShopItem::Charge, 3);
+        let paddle = harness.paddle();
+        let tint = |harness: &mut Harness| {
+            let mut query = harness
+                .world_mut()
+                .query_filtered::<&Sprite, With<PaddleBottomSprite>>();
+            query.single(harness.world()).color
+        };
+        let idle = Color::srgb(PADDLE_BLOOM, PADDLE_BLOOM, PADDLE_BLOOM);
+
+        KeyCode::Space.press(harness.world_mut());
+        harness.step(120);
+        assert!(harness.balls().is_empty());
+        assert!(harness.world().get::<Charge>(paddle).unwrap().0 >= 1.0);
+        assert_ne!(tint(&mut harness), idle);
+        KeyCode::Space.release(harness.world_mut());
+        harness.step(1);
+
+        let ball = harness.balls()[0];
+        let stats = *harness.world().get::<BallStats>(ball).unwrap();
+        let plain = BallStats::new(
+            harness.world().resource::<ShopStats>(),
+            harness.world().resource::<BallSpeedRules>(),
+            harness.world().resource::<BallLossRules>(),
+            BallKind::Basic,
+        );
+        assert_eq!(stats.damage, plain.damage * 2);
+        assert!(harness.ball_velocity(ball).length() > plain.min_speed * 1.5);
+        assert_eq!(harness.world().get::<Charge>(paddle).unwrap().0, 0.0);
+        assert_eq!(tint(&mut harness), idle);
+    }
+
+    #[test]
+    fn manual_rotation_is_unlocked_holds_its_angle_and_toggles_off() {
+        let mut harness = Harness::new();
+        let paddle = harness.paddle();
+        let angle = |harness: &Harness| {
+            harness
+                .world()
+                .get::<Transform>(paddle)
+                .unwrap()
+                .rotation
+                .to_euler(EulerRot::XYZ)
+                .2
+                .to_degrees()
+        };
+
+        KeyCode::KeyC.press(harness.world_mut());
+        harness.step(60);
+        assert!(angle(&harness).abs() < 1.0, "{}", angle(&harness));
+        KeyCode::KeyC.release(harness.world_mut());
+
+        harness
+            .world_mut()
+            .resource_mut::<ShopStats>()
+            .set_level(ShopItem::Rotate, 3);
+        KeyCode::KeyC.press(harness.world_mut());
+        harness.step(60);
+        KeyCode::KeyC.release(harness.world_mut());
+        harness.step(30);
+        assert!((angle(&harness) + 30.0).abs() < 2.0, "{}", angle(&harness));
+
+        // back to tilting with the movement, which levels out when standing still
+        harness.tap(KeyCode::KeyX);
+        assert_eq!(
+            harness.world().get::<TiltMode>(paddle),
+            Some(&TiltMode::Auto)
+        );
+        harness.step(120);
+        assert!(angle(&harness).abs() < 1.0, "{}", angle(&harness));
+    }
+}
@@ -2,7 +2,7 @@ use std::time::Duration;
 
 use bevy::{ecs::query, prelude::*};
 use bevy_rapier2d::{
-    plugin::{NoUserData, RapierPhysicsPlugin},
+    plugin::{NoUserData, PhysicsSet, RapierPhysicsPlugin},
     prelude::*,
     render::RapierDebugRenderPlugin,
 };
@@ -10,13 +10,19 @@ use rand::Rng;
 
 use crate::{
     app_state::AppState,
-    ball::{self, CollectedResources},
-    blocks::{BLOCK_GROUP_OFFSET, Block, BlockType, HitPoints, block_break},
+    ball::{
+        self, BallBounceEvent, BallReturnedEvent, BallStats, BounceSurface, CollectedResources,
+        PierceBlockEvent,
+    },
+    behaviours::{BlockBehaviour, BlockExplosionEvent, SlowBallEvent},
+    blocks::{
+        BLOCK_GROUP_OFFSET, Block, BlockPosition, BlockRegistry, BlockType, HitPoints, Wall,
+        block_break,
+    },
+    loadout::{BallHitBlockEvent, BallKind},
     paddle::Paddle,
     particles::BoxParticlesEvent,
-    shop::ShopStats,
     shoppanel::{ShopPanel, UpdateShopPanelsEvent},
-    statsbar::UpdateStatsBarResourcesEvent,
 };
 use crate::{ball::Ball, blocks::DespawnHack};
 
@@ -34,29 +40,65 @@ impl Plugin for PhysicsPlugin {
             // .add_plugins(RapierDebugRenderPlugin::default())
             .add_systems(
                 PostUpdate,
-                process_collisions.run_if(in_state(AppState::Game)),
+                process_collisions
+                    .after(PhysicsSet::Writeback)
+                    .run_if(in_state(AppState::Game)),
             );
     }
 }
 
-fn process_collisions(
+type BallQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static mut CollectedResources,
+        &'static BallKind,
+        &'static mut BallStats,
+    ),
+    With<Ball>,
+>;
+
+pub fn process_collisions(
     mut reader: EventReader<CollisionEvent>,
-    mut ball_query: Query<(Entity, &Transform, &mut CollectedResources), With<Ball>>,
+    mut ball_query: BallQuery,
     mut paddle_query: Query<(Entity, &mut CollectedResources), (With<Paddle>, Without<Ball>)>,
-    mut block_query: Query<(Entity, &mut HitPoints, &Transform, &Collider, &Block), Without<Ball>>,
+    mut block_query: Query<
+        (
+            Entity,
+            &mut HitPoints,
+            &Transform,
+            &Collider,
+            &Block,
+            &BlockPosition,
+            Option<&BlockBehaviour>,
+        ),
+        Without<Ball>,
+    >,
     mut shop_panel_query: Query<(Entity, &mut ShopPanel)>,
+    wall_query: Query<(), With<Wall>>,
     camera_query: Query<(Entity, &OrthographicProjection), With<Camera>>,
-    shop_stats: Res<ShopStats>,
+    registry: Res<BlockRegistry>,
     mut commands: Commands,
 ) {
     for &collision in reader.read() {
         match collision {
             CollisionEvent::Started(lhs, rhs, collision_event_flags) => {
-                if let Ok((entity, mut hitpoints, transform, collider, block)) =
-                    block_query.get_mut(lhs)
+                if let Ok((
+                    entity,
+                    mut hitpoints,
+                    transform,
+                    collider,
+                    block,
+                    position,
+                    behaviour,
+                )) = block_query.get_mut(lhs)
                 {
                     on_block_hit(
                         block,
+                        position,
+                        behaviour,
                         hitpoints.as_mut(),
                         transform,
                         collider,
@@ -64,13 +106,22 @@ fn process_collisions(
                         rhs,
                         &mut commands,
                         &mut ball_query,
-                        &shop_stats,
+                        &registry,
                     );
-                } else if let Ok((entity, mut hitpoints, transform, collider, block)) =
-                    block_query.get_mut(rhs)
+                } else if let Ok((
+                    entity,
+                    mut hitpoints,
+                    transform,
+                    collider,
+                    block,
+                    position,
+                    behaviour,
+                )) = block_query.get_mut(rhs)
                 {
                     on_block_hit(
                         block,
+                        position,
+                        behaviour,
                         hitpoints.as_mut(),
                         transform,
                         collider,
@@ -78,7 +129,7 @@ fn process_collisions(
                         lhs,
                         &mut commands,
                         &mut ball_query,
-                        &shop_stats,
+                        &registry,
                     );
                 }
                 // Process the paddle collisions. Use 'else if' to avoid reprocessing any block collisions.
@@ -90,6 +141,7 @@ fn process_collisions(
                         &mut commands,
                         &mut ball_query,
                         &camera_query,
+                        &registry,
                     );
                 } else if let Ok((entity, mut collected_resources)) = paddle_query.get_mut(rhs) {
                     on_paddle_hit(
@@ -99,6 +151,23 @@ fn process_collisions(
                         &mut commands,
                         &mut ball_query,
                         &camera_query,
+                        &registry,
+                    );
+                }
+                // Walls only matter for speeding balls up.
+                else if wall_query.contains(lhs) && ball_query.contains(rhs) {
+                    commands.trigger_targets(
+                        BallBounceEvent {
+                            surface: BounceSurface::Wall,
+                        },
+                        rhs,
+                    );
+                } else if wall_query.contains(rhs) && ball_query.contains(lhs) {
+                    commands.trigger_targets(
+                        BallBounceEvent {
+                            surface: BounceSurface::Wall,
+                        },
+                        lhs,
                     );
                 }
                 // Process shop panel collisions.
@@ -125,28 +194,74 @@ fn process_collisions(
 
 fn on_block_hit(
     block: &Block,
+    position: &BlockPosition,
+    behaviour: Option<&BlockBehaviour>,
     hitpoints: &mut HitPoints,
     transform: &Transform,
     collider: &Collider,
     entity: Entity,
     other: Entity,
     commands: &mut Commands,
-    ball_query: &mut Query<(Entity, &Transform, &mut CollectedResources), With<Ball>>,
-    shop_stats: &Res<ShopStats>,
+    ball_query: &mut BallQuery,
+    registry: &BlockRegistry,
 ) {
     // skip if we aren't hitting a ball
-    if let Ok((_, _, mut collected_resources)) = ball_query.get_mut(other) {
-        match hitpoints.damage(shop_stats.damage()) {
-            Ok(_) => {}
-            Err(_) => {
-                commands.entity(entity).insert(DespawnHack);
+    if let Ok((_, _, mut collected_resources, kind, mut stats)) = ball_query.get_mut(other) {
+        if let Some(BlockBehaviour::Slippery { speed_factor }) = behaviour {
+            commands.trigger_targets(
+                SlowBallEvent {
+                    speed_factor: *speed_factor,
+                },
+                other,
+            );
+        }
+
+        let (broken, pierces) = match behaviour {
+            Some(BlockBehaviour::Unbreakable) => (false, false),
+            _ => {
+                let pierces = stats.pierce > 0 && stats.damage > hitpoints.current();
+                let broken = hitpoints.damage(stats.damage).is_err();
+                if pierces {
+                    stats.pierce -= 1;
+                    commands.trigger_targets(PierceBlockEvent, other);
+                }
+                (broken, pierces)
+            }
+        };
+        if broken {
+            commands.entity(entity).insert(DespawnHack);
+
+            // Update CollectedResources for the corresponding ball
+            collected_resources.add_many(block.0, kind.resource_yield(&mut rand::rng()));
 
-                // Update CollectedResources for the corresponding ball
-                collected_resources.add(block.0);
+            block_break(block.0, transform, commands, registry);
 
-                block_break(block.0, transform, commands);
+            if let Some(BlockBehaviour::Explosive { radius, damage }) = behaviour {
+                commands.trigger(BlockExplosionEvent {
+                    position: *position,
+                    radius: *radius,
+                    damage: *damage,
+                    ball: other,
+                });
             }
         }
+
+        // a pierce keeps the ball's speed, only a real bounce speeds it up
+        if !pierces {
+            commands.trigger_targets(
+                BallBounceEvent {
+                    surface: BounceSurface::Block,
+                },
+                other,
+            );
+        }
+        commands.trigger_targets(
+            BallHitBlockEvent {
+                block: entity,
+                position: *position,
+            },
+            other,
+        );
     }
 }
 
@@ -155,14 +270,21 @@ fn on_paddle_hit(
     entity: Entity,
     other: Entity,
     commands: &mut Commands,
-    ball_query: &mut Query<(Entity, &Transform, &mut CollectedResources), With<Ball>>,
+    ball_query: &mut BallQuery,
     camera_query: &Query<(Entity, &OrthographicProjection), With<Camera>>,
+    registry: &BlockRegistry,
 ) {
     let (_, orthoproj) = camera_query.get_single().expect("Need single camera.");
     let half_screen_size = orthoproj.area.height() / 2.0;
 
     // check if collision is with a ball
-    if let Ok((_, ball_transform, mut ball_collected_resources)) = ball_query.get_mut(other) {
+    if let Ok((_, ball_transform, mut ball_collected_resources, _, _)) = ball_query.get_mut(other) {
+        commands.trigger_targets(
+            BallBounceEvent {
+                surface: BounceSurface::Paddle,
+            },
+            other,
+        );
         let mut rng = rand::rng();
         for (block_type, count) in &ball_collected_resources.counts {
             let num_spawns: u32;
@@ -175,12 +297,9 @@ fn on_paddle_hit(
                 _ => num_spawns = 9,
             }
 
-            let bloom_color = Color::srgba(
-                block_type.colour().to_srgba().red * 1.1,
-                block_type.colour().to_srgba().green * 1.1,
-                block_type.colour().to_srgba().blue * 1.1,
-                1.0,
-            );
+            let colour = registry.get(*block_type).colour.to_srgba();
+            let bloom_color =
+                Color::srgba(colour.red * 1.1, colour.green * 1.1, colour.blue * 1.1, 1.0);
 
             for _ in 0..num_spawns {
                 commands.trigger(BoxParticlesEvent {
@@ -200,11 +319,99 @@ fn on_paddle_hit(
             }
         }
 
+        if ball_collected_resources
+            .counts
+            .values()
+            .all(|count| *count == 0)
+        {
+            return;
+        }
         // add collected resources to paddle
         collected_resources.combine(&*ball_collected_resources);
         // clear the ball's collected resources
         ball_collected_resources.clear();
 
-        commands.trigger(UpdateStatsBarResourcesEvent);
+        commands.trigger_targets(BallReturnedEvent, other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        ball::{BallStats, PreviousVelocity},
+        harness::{Harness, cell_centre},
+        shop::{ShopItem, ShopStats},
+    };
+
+    #[test]
+    fn piercing_ball_spends_pierce_instead_of_bouncing() {
+        let mut harness = Harness::new();
+        {
+            let mut stats = harness.world_mut().resource_mut::<ShopStats>();
+            stats.set_level(ShopItem::Pierce, 2);
+            // pierces only when the damage is more than the block has left
+            stats.set_level(ShopItem::Damage, 2);
+        }
+        let block = harness.spawn_block(20, 4, "light_blue");
+        harness.set_paddle_position(cell_centre(20, 0));
+
+        harness.fire();
+        let ball = harness.balls()[0];
+        let pierce = harness.world().get::<BallStats>(ball).unwrap().pierce;
+        for _ in 0..120 {
+            harness.step(1);
+            if harness.world().get::<DespawnHack>(block).is_some() {
+                break;
+            }
+        }
+        // the harness has no terrain systems, so clear it out like despawn_hack would
+        harness.world_mut().despawn(block);
+        harness.step(1);
+
+        assert!(harness.ball_velocity(ball).y < 0.0, "ball bounced");
+        assert_eq!(
+            harness.world().get::<BallStats>(ball).unwrap().pierce,
+            pierce - 1
+        );
+    }
+
+    #[test]
+    fn piercing_ball_keeps_its_speed() {
+        let mut harness = Harness::new();
+        {
+            let mut stats = harness.world_mut().resource_mut::<ShopStats>();
+            stats.set_level(ShopItem::Pierce, 2);
+            stats.set_level(ShopItem::Damage, 2);
+        }
+        let block = harness.spawn_block(20, 4, "light_blue");
+        harness.set_paddle_position(cell_centre(20, 0));
+
+        harness.fire();
+        let ball = harness.balls()[0];
+        let linvel = Vec2::NEG_Y * 200.0;
+        harness
+            .world_mut()
+            .get_mut::<Velocity>(ball)
+            .unwrap()
+            .linvel = linvel;
+        harness
+            .world_mut()
+            .get_mut::<PreviousVelocity>(ball)
+            .unwrap()
+            .linvel = linvel;
+        for _ in 0..120 {
+            harness.step(1);
+            if harness.world().get::<DespawnHack>(block).is_some() {
+                break;
+            }
+        }
+        // no terrain systems here either
+        harness.world_mut().despawn(block);
+        harness.step(1);
+
+        let velocity = harness.ball_velocity(ball);
+        assert!((velocity - linvel).length() < 0.01, "{}", velocity);
     }
 }
@@ -0,0 +1,141 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    app_state::AppState,
+    ball::{BallLostEvent, CollectedResources},
+    blocks::{BlockRegistry, BlockType},
+    paddle::Paddle,
+    shop::ShopStats,
+    statsbar::UpdateStatsBarResourcesEvent,
+};
+
+pub struct PickupsPlugin;
+
+impl Plugin for PickupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            collect_pickups.run_if(in_state(AppState::Game)),
+        )
+        .add_observer(drop_pickups);
+    }
+}
+
+const PICKUP_SIZE: f32 = 8.0;
+const PICKUP_SCATTER: f32 = 25.0;
+/// Balls that fly off the top drop their resources back near the surface.
+const PICKUP_MAX_Y: f32 = 900.0;
+/// How far past the edge of the paddle a pickup can be collected from.
+const PICKUP_REACH: f32 = 20.0;
+
+/// Resources a lost ball was carrying, waiting for the paddle to fly over them.
+#[derive(Component, Debug)]
+pub struct Pickup {
+    pub block_type: BlockType,
+    pub count: u32,
+}
+
+fn drop_pickups(
+    trigger: Trigger<BallLostEvent>,
+    registry: Res<BlockRegistry>,
+    mut commands: Commands,
+) {
+    debug!("Dropping pickups from a lost ball ({:?})", trigger.reason);
+    let mut rng = rand::rng();
+    let centre = Vec2::new(trigger.position.x, trigger.position.y.min(PICKUP_MAX_Y));
+    for (block_type, count) in &trigger.resources {
+        if *count == 0 {
+            continue;
+        }
+        let offset =
+            Vec2::from_angle(rng.random_range(0.0..TAU)) * rng.random_range(0.0..PICKUP_SCATTER);
+        commands.spawn((
+            Pickup {
+                block_type: *block_type,
+                count: *count,
+            },
+            Sprite::from_color(registry.get(*block_type).colour, Vec2::splat(PICKUP_SIZE)),
+            Transform::from_translation((centre + offset).extend(5.0)),
+            StateScoped(AppState::Game),
+            Name::new("Pickup"),
+        ));
+    }
+}
+
+fn collect_pickups(
+    pickup_query: Query<(Entity, &Pickup, &Transform)>,
+    mut paddle_query: Query<(&Transform, &mut CollectedResources), With<Paddle>>,
+    stats: Res<ShopStats>,
+    mut commands: Commands,
+) {
+    let Ok((paddle_transform, mut collected_resources)) = paddle_query.get_single_mut() else {
+        return;
+    };
+    let reach = stats.size() / 2.0 + PICKUP_REACH;
+    let mut collected = false;
+    for (entity, pickup, transform) in pickup_query.iter() {
+        if transform
+            .translation
+            .truncate()
+            .distance(paddle_transform.translation.truncate())
+            > reach
+        {
+            continue;
+        }
+        collected_resources.add_many(pickup.block_type, pickup.count);
+        commands.entity(entity).despawn_recursive();
+        collected = true;
+    }
+    if collected {
+        commands.trigger(UpdateStatsBarResourcesEvent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{ball::BallLossRules, harness::Harness};
+
+    #[test]
+    fn expired_ball_drops_pickups_for_the_paddle() {
+        let mut harness = Harness::new();
+        harness.world_mut().resource_mut::<BallLossRules>().lifetime = Some(0.5);
+        harness.set_paddle_position(Vec2::new(0.0, 800.0));
+        harness.fire();
+        // out of the way so the pickup isn't collected straight away
+        harness.set_paddle_position(Vec2::new(300.0, 800.0));
+        let ball = harness.balls()[0];
+        let purple = harness.block_type("purple");
+        harness
+            .world_mut()
+            .get_mut::<CollectedResources>(ball)
+            .unwrap()
+            .add_many(purple, 3);
+
+        for _ in 0..60 {
+            harness.step(1);
+            if harness.balls().is_empty() {
+                break;
+            }
+        }
+        assert!(harness.balls().is_empty(), "ball never expired");
+
+        let pickup = harness
+            .world_mut()
+            .query::<(&Pickup, &Transform)>()
+            .iter(harness.world())
+            .map(|(pickup, transform)| (pickup.count, transform.translation.truncate()))
+            .next();
+        let (count, position) = pickup.expect("lost ball dropped a pickup");
+        assert_eq!(count, 3);
+
+        harness.set_paddle_position(position);
+        harness.step(1);
+        let paddle = harness.paddle();
+        assert_eq!(harness.collected(paddle, "purple"), 3);
+    }
+}
@@ -0,0 +1,402 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::{
+    ActiveCollisionTypes, ActiveEvents, Collider, CollisionEvent, CollisionGroups, Sensor,
+};
+use leafwing_input_manager::prelude::ActionState;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    asset_loading::{GameImageAssets, RefineryAssets},
+    ball::CollectedResources,
+    blocks::{BlockRegistry, BlockType, DefinitionError},
+    paddle::{Paddle, PaddleAction},
+    physics::PADDLE_SHOP_GROUP,
+    shop::try_buy,
+    shoppanel::ShopPanel,
+    statsbar::UpdateStatsBarResourcesEvent,
+};
+
+pub struct RefineryPlugin;
+
+impl Plugin for RefineryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Game), spawn_refineries)
+            .add_systems(
+                FixedUpdate,
+                (use_refinery, run_batches)
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(
+                PostUpdate,
+                (enable_refineries, update_refinery_labels)
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
+            );
+    }
+}
+
+const REFINERY_WIDTH: f32 = 95.0;
+/// Stations sit in a row above the shop panels.
+const REFINERY_ROW: Vec2 = Vec2::new(-240.0, 430.0);
+const REFINERY_SPACING: f32 = 120.0;
+
+/// Conversion recipes as authored in `refinery.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct RefineryDefinitions {
+    pub recipes: Vec<RecipeDefinition>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RecipeDefinition {
+    /// Block id and count taken per batch.
+    pub input: (String, u32),
+    /// Block id and count given per batch.
+    pub output: (String, u32),
+    /// Seconds a batch takes, 0 converts straight away.
+    #[serde(default)]
+    pub batch_time: f32,
+}
+
+/// Recipe with its block ids looked up in the registry.
+#[derive(Debug, Clone, Copy)]
+pub struct Recipe {
+    pub input: (BlockType, u32),
+    pub output: (BlockType, u32),
+    pub batch_time: f32,
+}
+
+/// Recipes in `refinery.ron` order, one station each.
+#[derive(Resource, Debug)]
+pub struct RefineryRecipes(pub Vec<Recipe>);
+
+impl FromWorld for RefineryRecipes {
+    fn from_world(world: &mut World) -> Self {
+        let refinery_assets = world.resource::<RefineryAssets>();
+        let definitions = world
+            .resource::<Assets<RefineryDefinitions>>()
+            .get(&refinery_assets.recipes)
+            .expect("Refinery recipes should be loaded.");
+        RefineryRecipes::new(definitions, world.resource::<BlockRegistry>())
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+const REFINERY_FILE: &str = "refinery.ron";
+
+impl RefineryRecipes {
+    pub fn new(
+        definitions: &RefineryDefinitions,
+        registry: &BlockRegistry,
+    ) -> Result<Self, DefinitionError> {
+        let resolve = |(id, count): &(String, u32)| {
+            registry
+                .resolve(REFINERY_FILE, id)
+                .map(|block_type| (block_type, *count))
+        };
+        definitions
+            .recipes
+            .iter()
+            .map(|recipe| {
+                Ok(Recipe {
+                    input: resolve(&recipe.input)?,
+                    output: resolve(&recipe.output)?,
+                    batch_time: recipe.batch_time,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Station that turns common resources into rarer ones, used like a shop panel.
+#[derive(Component, Debug)]
+pub struct Refinery {
+    pub recipe: Recipe,
+    /// Paddle is over the station.
+    pub enabled: bool,
+    /// Batches paid for but not finished yet.
+    pub queued: u32,
+    /// Seconds into the current batch.
+    pub progress: f32,
+}
+
+impl Refinery {
+    pub fn new(recipe: Recipe) -> Self {
+        Self {
+            recipe,
+            enabled: false,
+            queued: 0,
+            progress: 0.0,
+        }
+    }
+}
+
+#[derive(Component)]
+struct RefineryLabel;
+
+fn spawn_refineries(
+    mut commands: Commands,
+    assets: Res<GameImageAssets>,
+    recipes: Res<RefineryRecipes>,
+    registry: Res<BlockRegistry>,
+) {
+    for (index, recipe) in recipes.0.iter().enumerate() {
+        let position = REFINERY_ROW + Vec2::X * index as f32 * REFINERY_SPACING;
+        commands
+            .spawn((
+                Sprite {
+                    image: assets.shop_background.clone(),
+                    custom_size: Some(Vec2::new(REFINERY_WIDTH, REFINERY_WIDTH)),
+                    color: Color::srgb(0.5, 0.5, 0.5),
+                    ..Default::default()
+                },
+                Refinery::new(*recipe),
+                Transform::from_translation(position.extend(-50.0)),
+                Name::new("Refinery"),
+                (
+                    Collider::cuboid(REFINERY_WIDTH / 2.0, REFINERY_WIDTH / 2.0),
+                    ActiveCollisionTypes::all(),
+                    ActiveEvents::COLLISION_EVENTS,
+                    Sensor,
+                    CollisionGroups::new(PADDLE_SHOP_GROUP, PADDLE_SHOP_GROUP),
+                ),
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Sprite {
+                        image: registry.get(recipe.input.0).image.clone(),
+                        custom_size: Some(Vec2::new(25.0, 25.0)),
+                        ..Default::default()
+                    },
+                    Transform::from_xyz(-20.0, 0.0, 1.0),
+                ));
+                parent.spawn((
+                    Sprite {
+                        image: registry.get(recipe.output.0).image.clone(),
+                        custom_size: Some(Vec2::new(25.0, 25.0)),
+                        ..Default::default()
+                    },
+                    Transform::from_xyz(20.0, 0.0, 1.0),
+                ));
+                parent.spawn((
+                    RefineryLabel,
+                    Text2d::new(""),
+                    TextFont {
+                        font_size: 14.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Transform::from_xyz(0.0, -30.0, 1.0),
+                ));
+            });
+    }
+}
+
+/// Light up stations the paddle is touching, like `process_collisions` does for shop panels.
+fn enable_refineries(
+    mut reader: EventReader<CollisionEvent>,
+    mut query: Query<(&mut Refinery, &mut Sprite)>,
+) {
+    for &collision in reader.read() {
+        let (lhs, rhs, enabled) = match collision {
+            CollisionEvent::Started(lhs, rhs, _) => (lhs, rhs, true),
+            CollisionEvent::Stopped(lhs, rhs, _) => (lhs, rhs, false),
+        };
+        let station = if query.contains(lhs) { lhs } else { rhs };
+        let Ok((mut refinery, mut sprite)) = query.get_mut(station) else {
+            continue;
+        };
+        refinery.enabled = enabled;
+        sprite.color = if enabled {
+            Color::WHITE
+        } else {
+            Color::srgb(0.5, 0.5, 0.5)
+        };
+    }
+}
+
+/// Pay for a batch at the station the paddle is over.
+fn use_refinery(
+    mut paddle_query: Query<(&ActionState<PaddleAction>, &mut CollectedResources), With<Paddle>>,
+    mut refinery_query: Query<&mut Refinery>,
+    shop_panel_query: Query<&ShopPanel>,
+    mut commands: Commands,
+) {
+    let Ok((action_state, mut collected_resources)) = paddle_query.get_single_mut() else {
+        return;
+    };
+    if !action_state.just_pressed(&PaddleAction::Interact) {
+        return;
+    }
+    // a shop panel under the paddle takes the press
+    if shop_panel_query.iter().any(|panel| panel.enabled) {
+        return;
+    }
+
+    for mut refinery in refinery_query.iter_mut() {
+        if !refinery.enabled {
+            continue;
+        }
+        let (input, count) = refinery.recipe.input;
+        if !try_buy(&[(input, count)].into(), &mut collected_resources.counts) {
+            info!("Not enough resources to refine: {:?}", refinery.recipe);
+            continue;
+        }
+        if refinery.recipe.batch_time > 0.0 {
+            refinery.queued += 1;
+        } else {
+            let (output, count) = refinery.recipe.output;
+            collected_resources.add_many(output, count);
+        }
+        commands.trigger(UpdateStatsBarResourcesEvent);
+    }
+}
+
+/// Work through queued batches, paying each one out to the paddle when it's done.
+fn run_batches(
+    mut paddle_query: Query<&mut CollectedResources, With<Paddle>>,
+    mut refinery_query: Query<&mut Refinery>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let Ok(mut collected_resources) = paddle_query.get_single_mut() else {
+        return;
+    };
+
+    for mut refinery in refinery_query.iter_mut() {
+        if refinery.queued == 0 {
+            continue;
+        }
+        refinery.progress += time.delta_secs();
+        if refinery.progress >= refinery.recipe.batch_time {
+            refinery.progress = 0.0;
+            refinery.queued -= 1;
+            let (output, count) = refinery.recipe.output;
+            collected_resources.add_many(output, count);
+            commands.trigger(UpdateStatsBarResourcesEvent);
+        }
+    }
+}
+
+fn update_refinery_labels(
+    refinery_query: Query<&Refinery>,
+    mut label_query: Query<(&Parent, &mut Text2d), With<RefineryLabel>>,
+) {
+    for (parent, mut text) in label_query.iter_mut() {
+        let Ok(refinery) = refinery_query.get(parent.get()) else {
+            continue;
+        };
+        let recipe = &refinery.recipe;
+        let mut label = format!("{} -> {}", recipe.input.1, recipe.output.1);
+        if refinery.queued > 0 {
+            label += &format!(
+                "\nx{} {:.1}s",
+                refinery.queued,
+                recipe.batch_time - refinery.progress
+            );
+        } else if refinery.enabled {
+            label += "\n<E> refine";
+        }
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        harness::{Harness, registry},
+        shop::{ShopItem, ShopStats},
+        shoppanel::PanelKind,
+    };
+
+    #[test]
+    fn unknown_refinery_block_fails_to_load() {
+        let definitions: RefineryDefinitions = bevy::asset::ron::from_str(
+            "(recipes: [(input: (\"blue\", 4), output: (\"gold\", 1))])",
+        )
+        .unwrap();
+        let error = RefineryRecipes::new(&definitions, &registry()).unwrap_err();
+        assert_eq!(error.file, "refinery.ron");
+        assert!(error.message.contains("gold"), "{}", error);
+    }
+
+    #[test]
+    fn refinery_converts_resources_instantly_or_in_batches() {
+        let mut harness = Harness::new().with_resources(&[("light_blue", 120), ("pink", 45)]);
+        let paddle = harness.paddle();
+        let light_blue = harness.block_type("light_blue");
+        let purple = harness.block_type("purple");
+        let pink = harness.block_type("pink");
+        let red = harness.block_type("red");
+
+        let mut refinery = Refinery::new(Recipe {
+            input: (light_blue, 50),
+            output: (purple, 1),
+            batch_time: 0.0,
+        });
+        refinery.enabled = true;
+        let instant = harness.world_mut().spawn(refinery).id();
+        harness.tap(KeyCode::KeyE);
+        harness.tap(KeyCode::KeyE);
+        harness.tap(KeyCode::KeyE);
+        assert_eq!(harness.collected(paddle, "light_blue"), 20);
+        assert_eq!(harness.collected(paddle, "purple"), 2);
+
+        harness
+            .world_mut()
+            .get_mut::<Refinery>(instant)
+            .unwrap()
+            .enabled = false;
+        let mut refinery = Refinery::new(Recipe {
+            input: (pink, 20),
+            output: (red, 1),
+            batch_time: 0.5,
+        });
+        refinery.enabled = true;
+        let timed = harness.world_mut().spawn(refinery).id();
+        harness.tap(KeyCode::KeyE);
+        harness.tap(KeyCode::KeyE);
+        assert_eq!(harness.collected(paddle, "pink"), 5);
+        assert_eq!(harness.world().get::<Refinery>(timed).unwrap().queued, 2);
+        assert_eq!(harness.collected(paddle, "red"), 0);
+
+        // 64 ticks a second, so one batch in and most of the way through the next
+        harness.step(40);
+        assert_eq!(harness.collected(paddle, "red"), 1);
+        harness.step(40);
+        assert_eq!(harness.collected(paddle, "red"), 2);
+        assert_eq!(harness.world().get::<Refinery>(timed).unwrap().queued, 0);
+    }
+
+    #[test]
+    fn shop_panel_takes_the_press_over_a_refinery() {
+        let mut harness = Harness::new().with_resources(&[("purple", 10), ("light_blue", 50)]);
+        harness.spawn_shop_panel(PanelKind::Upgrade(ShopItem::Damage));
+        let light_blue = harness.block_type("light_blue");
+        let purple = harness.block_type("purple");
+        let mut refinery = Refinery::new(Recipe {
+            input: (light_blue, 50),
+            output: (purple, 1),
+            batch_time: 0.0,
+        });
+        refinery.enabled = true;
+        harness.world_mut().spawn(refinery);
+        let paddle = harness.paddle();
+
+        harness.tap(KeyCode::KeyE);
+        assert_eq!(
+            harness
+                .world()
+                .resource::<ShopStats>()
+                .level(ShopItem::Damage),
+            2
+        );
+        assert_eq!(harness.collected(paddle, "light_blue"), 50);
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::blocks::{BlockRegistry, BlockType, DefinitionError};
+
+const ROOMS_FILE: &str = "rooms/*.room.ron";
+
+/// A hand-authored room as authored in `assets/rooms/*.room.ron`, stamped into the generated
+/// terrain by the world generator.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct RoomTemplate {
+    pub id: String,
+    /// Shallowest row the room can be placed at.
+    #[serde(default)]
+    pub min_depth: f32,
+    /// Characters that aren't in the legend keep the generated terrain.
+    pub legend: HashMap<char, RoomCell>,
+    /// Layout of the room from the top row down, one character per cell.
+    pub rows: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum RoomCell {
+    Empty,
+    /// Block id from `blocks.ron`.
+    Block(String),
+}
+
+/// What a room does to a single cell of the terrain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoomTile {
+    Keep,
+    Set(Option<BlockType>),
+}
+
+#[derive(Debug, Clone)]
+pub struct RoomInfo {
+    pub min_depth: f32,
+    pub tiles: Vec<Vec<RoomTile>>,
+}
+
+impl RoomInfo {
+    /// Resolve the legend against the registry.
+    pub fn new(template: &RoomTemplate, registry: &BlockRegistry) -> Result<Self, DefinitionError> {
+        let tiles = template
+            .rows
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|c| match template.legend.get(&c) {
+                        Some(RoomCell::Empty) => Ok(RoomTile::Set(None)),
+                        Some(RoomCell::Block(id)) => registry
+                            .find(id)
+                            .map(|block_type| RoomTile::Set(Some(block_type)))
+                            .ok_or_else(|| {
+                                DefinitionError::new(
+                                    ROOMS_FILE,
+                                    format!("unknown block id '{}' in room '{}'", id, template.id),
+                                )
+                            }),
+                        None => Ok(RoomTile::Keep),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            min_depth: template.min_depth,
+            tiles,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.tiles.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn tile(&self, column: usize, row: usize) -> RoomTile {
+        self.tiles
+            .get(row)
+            .and_then(|tiles| tiles.get(column))
+            .copied()
+            .unwrap_or(RoomTile::Keep)
+    }
+}
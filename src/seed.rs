@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+pub struct SeedPlugin;
+
+impl Plugin for SeedPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = Seed::from_overrides().unwrap_or_else(Seed::random);
+        // only the run code, so what's logged can be passed straight back in
+        info!("World run code: {}", seed.run_code());
+        app.insert_resource(seed);
+    }
+}
+
+/// Seed used for world generation. Two runs with the same seed dig the same world.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seed(pub u32);
+
+const SEED_ARG: &str = "--seed";
+const SEED_ENV: &str = "LDJAM57_SEED";
+const RUN_CODE_DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+impl Seed {
+    pub fn random() -> Self {
+        Self(rand::rng().random_range(0..17851437))
+    }
+
+    /// Look for a run code in the cli args, then the env, then the page url (wasm only).
+    pub fn from_overrides() -> Option<Self> {
+        [seed_from_args(), seed_from_env(), seed_from_url()]
+            .into_iter()
+            .flatten()
+            .find_map(|code| {
+                let seed = Self::from_run_code(&code);
+                if seed.is_none() {
+                    warn!("Ignoring invalid run code: {}", code);
+                }
+                seed
+            })
+    }
+
+    /// Short shareable form of the seed (base 36).
+    pub fn run_code(&self) -> String {
+        let mut value = self.0;
+        let mut code = Vec::new();
+        loop {
+            code.push(RUN_CODE_DIGITS[(value % 36) as usize]);
+            value /= 36;
+            if value == 0 {
+                break;
+            }
+        }
+        code.reverse();
+        String::from_utf8(code).expect("Run code digits are ascii.")
+    }
+
+    pub fn from_run_code(code: &str) -> Option<Self> {
+        let code = code.trim();
+        if code.is_empty() {
+            return None;
+        }
+        u32::from_str_radix(code, 36).ok().map(Self)
+    }
+}
+
+fn seed_from_args() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == SEED_ARG {
+            return args.next();
+        }
+        if let Some(code) = arg.strip_prefix("--seed=") {
+            return Some(code.to_string());
+        }
+    }
+    None
+}
+
+fn seed_from_env() -> Option<String> {
+    std::env::var(SEED_ENV).ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn seed_from_url() -> Option<String> {
+    const SEED_QUERY: &str = "seed";
+    let search = web_sys::window()?.location().search().ok()?;
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == SEED_QUERY)
+        .map(|(_, value)| value.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn seed_from_url() -> Option<String> {
+    None
+}
@@ -1,132 +1,306 @@
 use std::collections::HashMap;
 
 use bevy::prelude::*;
+use bevy_rapier2d::prelude::RapierConfiguration;
 use leafwing_input_manager::prelude::ActionState;
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
 
 use crate::{
     app_state::AppState,
+    asset_loading::UpgradeAssets,
     ball::CollectedResources,
-    blocks::BlockType,
+    blocks::{BlockRegistry, BlockType, DefinitionError},
     paddle::{Paddle, PaddleAction},
+    shoppanel::UpdateShopPanelsEvent,
+    statsbar::UpdateStatsBarResourcesEvent,
 };
 
 pub struct ShopPlugin;
 
 impl Plugin for ShopPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<ShopStats>()
-            .add_sub_state::<ShopState>()
+        app.add_sub_state::<ShopState>()
+            // not FixedUpdate, that stops while the shop is open
             .add_systems(
-                FixedUpdate,
+                Update,
                 check_for_shop_toggle.run_if(in_state(AppState::Game)),
-            );
+            )
+            .add_systems(OnEnter(ShopState::Open), pause_game)
+            .add_systems(OnExit(ShopState::Open), resume_game);
     }
 }
 
-#[derive(Component, Debug)]
+/// Upgrade sold in the shop, also its id in `upgrades.ron`.
+#[derive(Component, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ShopItem {
     Damage,
     Speed,
     Capacity,
     Size,
+    Pierce,
+    Charge,
+    Burst,
+    Dash,
+    Rotate,
+    /// Unlocks the next ball kind for the loadout.
+    Balls,
 }
 
-#[derive(Resource)]
+/// Paddle and ball stat an upgrade level can set.
+#[derive(Deserialize, EnumIter, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stat {
+    Damage,
+    Speed,
+    Capacity,
+    Size,
+    Pierce,
+    MaxCharge,
+    Burst,
+    DashCooldown,
+    DashSpeed,
+    MaxRotation,
+    BallKinds,
+}
+
+/// Upgrade tree as authored in `upgrades.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct UpgradeDefinitions {
+    /// Fraction of the resources spent on upgrades given back by a respec.
+    pub refund: f32,
+    pub upgrades: Vec<UpgradeDefinition>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UpgradeDefinition {
+    pub item: ShopItem,
+    pub name: String,
+    /// Level 1 first, its cost is ignored since every upgrade starts there.
+    pub levels: Vec<UpgradeLevel>,
+    /// Upgrade levels that have to be bought before this one can be.
+    #[serde(default)]
+    pub requires: Vec<UpgradeRequirement>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UpgradeLevel {
+    /// Block ids and counts.
+    #[serde(default)]
+    pub cost: Vec<(String, u32)>,
+    #[serde(default)]
+    pub effects: HashMap<Stat, f32>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct UpgradeRequirement {
+    pub item: ShopItem,
+    pub level: u8,
+}
+
+/// Current upgrade levels, with the tree from `upgrades.ron` they index into.
+#[derive(Resource, Debug)]
 pub struct ShopStats {
-    pub damage_level: u8,
-    pub speed_level: u8,
-    pub capacity_level: u8,
-    pub size_level: u8,
+    upgrades: HashMap<ShopItem, UpgradeDefinition>,
+    /// Upgrades in the order `upgrades.ron` lists them.
+    order: Vec<ShopItem>,
+    /// Cost of each level with its block ids looked up, level 1 first.
+    costs: HashMap<ShopItem, Vec<HashMap<BlockType, u32>>>,
+    levels: HashMap<ShopItem, u8>,
+    refund: f32,
+    /// What was actually paid for each level bought, oldest first, so refunds don't depend on
+    /// the current cost tables.
+    ledger: HashMap<ShopItem, Vec<HashMap<BlockType, u32>>>,
 }
 
+impl FromWorld for ShopStats {
+    fn from_world(world: &mut World) -> Self {
+        let upgrade_assets = world.resource::<UpgradeAssets>();
+        let definitions = world
+            .resource::<Assets<UpgradeDefinitions>>()
+            .get(&upgrade_assets.definitions)
+            .expect("Upgrade definitions should be loaded.");
+        ShopStats::new(definitions, world.resource::<BlockRegistry>())
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+const UPGRADES_FILE: &str = "upgrades.ron";
+
 impl ShopStats {
-    pub fn damage(&self) -> u16 {
-        if self.damage_level > 5 {
-            return 20;
+    /// Check the upgrade tree and look up its costs, failing on anything that would otherwise
+    /// only show up once that upgrade is used.
+    pub fn new(
+        definitions: &UpgradeDefinitions,
+        registry: &BlockRegistry,
+    ) -> Result<Self, DefinitionError> {
+        let mut upgrades = HashMap::new();
+        let mut costs = HashMap::new();
+        for upgrade in &definitions.upgrades {
+            if upgrade.levels.is_empty() {
+                return Err(DefinitionError::new(
+                    UPGRADES_FILE,
+                    format!("upgrade {:?} needs at least one level", upgrade.item),
+                ));
+            }
+            let level_costs = upgrade
+                .levels
+                .iter()
+                .map(|level| registry.cost(UPGRADES_FILE, &level.cost))
+                .collect::<Result<Vec<_>, _>>()?;
+            costs.insert(upgrade.item, level_costs);
+            upgrades.insert(upgrade.item, upgrade.clone());
         }
-        self.damage_level as u16
-    }
-
-    pub fn damage_cost(&self) -> Option<HashMap<BlockType, u32>> {
-        match self.damage_level {
-            1 => Some(HashMap::from([(BlockType::Purple, 6)])),
-            2 => Some(HashMap::from([
-                (BlockType::LightPurple, 5),
-                (BlockType::Purple, 10),
-            ])),
-            3 => Some(HashMap::from([
-                (BlockType::Pink, 5),
-                (BlockType::Purple, 10),
-            ])),
-            4 => Some(HashMap::from([(BlockType::Red, 10)])),
-            5 => Some(HashMap::from([(BlockType::Orange, 10)])),
-            _ => None,
+        // stat() falls back on level 1, so every stat needs a value there
+        if let Some(stat) = Stat::iter().find(|stat| {
+            !definitions
+                .upgrades
+                .iter()
+                .any(|upgrade| upgrade.levels[0].effects.contains_key(stat))
+        }) {
+            return Err(DefinitionError::new(
+                UPGRADES_FILE,
+                format!("no upgrade sets {:?} at level 1", stat),
+            ));
         }
+
+        let order = definitions
+            .upgrades
+            .iter()
+            .map(|upgrade| upgrade.item)
+            .collect();
+        let levels = upgrades.keys().map(|&item| (item, 1)).collect();
+        Ok(Self {
+            upgrades,
+            order,
+            costs,
+            levels,
+            refund: definitions.refund,
+            ledger: HashMap::new(),
+        })
     }
 
-    pub fn speed(&self) -> f32 {
-        self.speed_level as f32 * 100.0
-    }
-
-    pub fn speed_cost(&self) -> Option<HashMap<BlockType, u32>> {
-        match self.speed_level {
-            1 => Some(HashMap::from([(BlockType::LightBlue, 50)])),
-            2 => Some(HashMap::from([(BlockType::LightBlue, 100)])),
-            3 => Some(HashMap::from([
-                (BlockType::LightBlue, 200),
-                (BlockType::DarkBlue, 50),
-            ])),
-            4 => Some(HashMap::from([
-                (BlockType::LightBlue, 500),
-                (BlockType::DarkBlue, 200),
-            ])),
-            _ => None,
-        }
+    pub fn items(&self) -> impl Iterator<Item = ShopItem> + '_ {
+        self.order.iter().copied()
     }
 
-    pub fn capacity(&self) -> u32 {
-        self.capacity_level as u32 * 3
+    pub fn name(&self, item: ShopItem) -> &str {
+        self.upgrades
+            .get(&item)
+            .map_or("Unknown Upgrade", |upgrade| upgrade.name.as_str())
+    }
+
+    pub fn level(&self, item: ShopItem) -> u8 {
+        self.levels.get(&item).copied().unwrap_or(1)
+    }
+
+    pub fn set_level(&mut self, item: ShopItem, level: u8) {
+        self.levels.insert(item, level);
     }
 
-    pub fn capacity_cost(&self) -> Option<HashMap<BlockType, u32>> {
-        match self.capacity_level {
-            1 => Some(HashMap::from([(BlockType::LightBlue, 10)])),
-            2 => Some(HashMap::from([(BlockType::Pink, 20)])),
-            3 => Some(HashMap::from([(BlockType::Purple, 40)])),
-            4 => Some(HashMap::from([(BlockType::Red, 5), (BlockType::Blue, 500)])),
-            _ => None,
+    /// Value of `stat` from the last bought level that sets it, looking through upgrades in
+    /// `upgrades.ron` order if more than one sets it.
+    pub fn stat(&self, stat: Stat) -> f32 {
+        self.order
+            .iter()
+            .map(|item| &self.upgrades[item])
+            .find_map(|upgrade| {
+                // a level past the last one in upgrades.ron gets the last one's effects
+                let bought = (self.level(upgrade.item) as usize).min(upgrade.levels.len());
+                upgrade.levels[..bought]
+                    .iter()
+                    .rev()
+                    .find_map(|level| level.effects.get(&stat).copied())
+            })
+            // never hit, new() makes sure level 1 sets every stat
+            .unwrap_or_default()
+    }
+
+    /// Resources needed for the next level of `item`, `None` once it's maxed out.
+    pub fn cost(&self, item: ShopItem) -> Option<HashMap<BlockType, u32>> {
+        self.costs
+            .get(&item)?
+            .get(self.level(item) as usize)
+            .cloned()
+    }
+
+    pub fn refund(&self) -> f32 {
+        self.refund
+    }
+
+    /// Resources a respec would give back right now.
+    pub fn refund_total(&self) -> HashMap<BlockType, u32> {
+        let mut total: HashMap<BlockType, u32> = HashMap::new();
+        for paid in self.ledger.values().flatten() {
+            for (&block_type, &count) in paid {
+                *total.entry(block_type).or_insert(0) += count;
+            }
         }
+        for count in total.values_mut() {
+            *count = (*count as f32 * self.refund).floor() as u32;
+        }
+        total.retain(|_, count| *count > 0);
+        total
+    }
+
+    /// First prerequisite of `item` that hasn't been bought yet.
+    pub fn missing_requirement(&self, item: ShopItem) -> Option<UpgradeRequirement> {
+        self.upgrades
+            .get(&item)?
+            .requires
+            .iter()
+            .find(|requirement| self.level(requirement.item) < requirement.level)
+            .copied()
+    }
+
+    pub fn damage(&self) -> u16 {
+        self.stat(Stat::Damage) as u16
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.stat(Stat::Speed)
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.stat(Stat::Capacity) as u32
     }
 
     pub fn size(&self) -> f32 {
-        self.size_level as f32 * 10.0 + 30.0
-    }
-
-    pub fn size_cost(&self) -> Option<HashMap<BlockType, u32>> {
-        match self.size_level {
-            1 => Some(HashMap::from([(BlockType::LightBlue, 10)])),
-            2 => Some(HashMap::from([
-                (BlockType::Red, 1),
-                (BlockType::LightPurple, 10),
-            ])),
-            3 => Some(HashMap::from([(BlockType::Red, 10), (BlockType::Pink, 10)])),
-            4 => Some(HashMap::from([
-                (BlockType::Orange, 5),
-                (BlockType::Pink, 10),
-            ])),
-            _ => None,
-        }
+        self.stat(Stat::Size)
     }
-}
 
-impl Default for ShopStats {
-    fn default() -> Self {
-        Self {
-            damage_level: 1,
-            speed_level: 1,
-            capacity_level: 1,
-            size_level: 1,
-        }
+    /// Blocks a ball can break through before it bounces.
+    pub fn pierce(&self) -> u32 {
+        self.stat(Stat::Pierce) as u32
+    }
+
+    /// Power of a fully charged shot, 1 until charge shots are unlocked.
+    pub fn max_charge(&self) -> f32 {
+        self.stat(Stat::MaxCharge)
+    }
+
+    /// Balls fired at once in burst mode, 1 until burst fire is unlocked.
+    pub fn burst(&self) -> u32 {
+        self.stat(Stat::Burst) as u32
+    }
+
+    /// Seconds between dashes.
+    pub fn dash_cooldown(&self) -> f32 {
+        self.stat(Stat::DashCooldown)
+    }
+
+    pub fn dash_speed(&self) -> f32 {
+        self.stat(Stat::DashSpeed)
+    }
+
+    /// Furthest the paddle can be rotated by hand in degrees, 0 until manual rotation is unlocked.
+    pub fn max_rotation(&self) -> f32 {
+        self.stat(Stat::MaxRotation)
+    }
+
+    /// How many of `BallKind::ALL` the loadout has unlocked.
+    pub fn ball_kinds(&self) -> usize {
+        self.stat(Stat::BallKinds) as usize
     }
 }
 
@@ -139,14 +313,12 @@ pub enum ShopState {
 }
 
 fn check_for_shop_toggle(
-    mut query: Query<&ActionState<PaddleAction>>,
-    time: Res<Time>,
-    mut commands: Commands,
+    query: Query<&ActionState<PaddleAction>>,
     shop_state: Res<State<ShopState>>,
     mut next_state: ResMut<NextState<ShopState>>,
 ) {
     for action in &query {
-        if action.just_pressed(&PaddleAction::Interact) {
+        if action.just_pressed(&PaddleAction::Shop) {
             next_state.set(if *shop_state.get() == ShopState::Open {
                 ShopState::Closed
             } else {
@@ -156,6 +328,21 @@ fn check_for_shop_toggle(
     }
 }
 
+/// Stop the clock and physics while the shop menu is open.
+fn pause_game(mut time: ResMut<Time<Virtual>>, mut rapier_query: Query<&mut RapierConfiguration>) {
+    time.pause();
+    for mut config in rapier_query.iter_mut() {
+        config.physics_pipeline_active = false;
+    }
+}
+
+fn resume_game(mut time: ResMut<Time<Virtual>>, mut rapier_query: Query<&mut RapierConfiguration>) {
+    time.unpause();
+    for mut config in rapier_query.iter_mut() {
+        config.physics_pipeline_active = true;
+    }
+}
+
 pub fn on_shop_item_pressed(
     In(entity): In<Entity>,
     query: Query<&ShopItem>,
@@ -167,24 +354,77 @@ pub fn on_shop_item_pressed(
         .get_single_mut()
         .expect("Need single paddle to try buy.");
     if let Ok(item) = query.get(entity) {
-        if let Some(cost) = match item {
-            ShopItem::Damage => shop_stats.damage_cost(),
-            ShopItem::Speed => shop_stats.speed_cost(),
-            ShopItem::Capacity => shop_stats.capacity_cost(),
-            ShopItem::Size => shop_stats.size_cost(),
-        } {
-            if try_buy(&cost, &mut resources.counts) {
-                match item {
-                    ShopItem::Damage => shop_stats.damage_level += 1,
-                    ShopItem::Speed => shop_stats.speed_level += 1,
-                    ShopItem::Capacity => shop_stats.capacity_level += 1,
-                    ShopItem::Size => shop_stats.size_level += 1,
-                }
-            } else {
-                info!("Failed to buy: {:?}", item);
-            }
-        }
+        purchase(
+            *item,
+            &mut shop_stats,
+            &mut resources.counts,
+            &mut commands,
+        );
+    }
+}
+
+/// An upgrade's level was bought or reset, after `ShopStats` has been updated.
+#[derive(Event, Debug)]
+pub struct UpgradeLevelChangedEvent {
+    pub item: ShopItem,
+}
+
+/// Buy the next level of `item` if its prerequisites are met and `owned` covers the cost.
+pub fn purchase(
+    item: ShopItem,
+    stats: &mut ShopStats,
+    owned: &mut HashMap<BlockType, u32>,
+    commands: &mut Commands,
+) -> bool {
+    if let Some(requirement) = stats.missing_requirement(item) {
+        info!(
+            "Can't buy {:?} before {:?} level {}",
+            item, requirement.item, requirement.level
+        );
+        return false;
     }
+    let Some(cost) = stats.cost(item) else {
+        return false;
+    };
+    if !try_buy(&cost, owned) {
+        info!("Failed to buy: {:?}", item);
+        return false;
+    }
+
+    stats.set_level(item, stats.level(item) + 1);
+    stats.ledger.entry(item).or_default().push(cost);
+    commands.trigger(UpgradeLevelChangedEvent { item });
+    commands.trigger(UpdateStatsBarResourcesEvent);
+    commands.trigger(UpdateShopPanelsEvent);
+    true
+}
+
+/// Reset every upgrade to level 1, giving back `refund` of what the ledger says was paid.
+pub fn respec(
+    stats: &mut ShopStats,
+    owned: &mut HashMap<BlockType, u32>,
+    commands: &mut Commands,
+) -> bool {
+    let bought: Vec<ShopItem> = stats
+        .items()
+        .filter(|&item| stats.level(item) > 1)
+        .collect();
+    if bought.is_empty() {
+        return false;
+    }
+
+    for (block_type, count) in stats.refund_total() {
+        *owned.entry(block_type).or_insert(0) += count;
+    }
+    info!("Respec, refunded: {:?}", stats.refund_total());
+    stats.ledger.clear();
+    for item in bought {
+        stats.set_level(item, 1);
+        commands.trigger(UpgradeLevelChangedEvent { item });
+    }
+    commands.trigger(UpdateStatsBarResourcesEvent);
+    commands.trigger(UpdateShopPanelsEvent);
+    true
 }
 
 pub fn try_buy(reqs: &HashMap<BlockType, u32>, owned: &mut HashMap<BlockType, u32>) -> bool {
@@ -210,3 +450,167 @@ pub fn try_buy(reqs: &HashMap<BlockType, u32>, owned: &mut HashMap<BlockType, u3
     }
     succeeded
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bevy_rapier2d::prelude::Collider;
+
+    use crate::{
+        harness::{Harness, registry},
+        paddle::TiltMode,
+        shoppanel::PanelKind,
+    };
+
+    #[test]
+    fn stat_set_by_two_upgrades_comes_from_the_first_listed() {
+        let definitions: UpgradeDefinitions = bevy::asset::ron::from_str(
+            "(
+                refund: 0.5,
+                upgrades: [
+                    (item: Damage, name: \"Damage\", levels: [(effects: {Damage: 1.0})]),
+                    (item: Speed, name: \"Speed\", levels: [(effects: {Damage: 2.0})]),
+                    (item: Size, name: \"Rest\", levels: [(effects: {
+                        Speed: 1.0, Capacity: 1.0, Size: 1.0, Pierce: 0.0, MaxCharge: 1.0,
+                        Burst: 1.0, DashCooldown: 1.0, DashSpeed: 1.0, MaxRotation: 0.0,
+                        BallKinds: 1.0,
+                    })]),
+                ],
+            )",
+        )
+        .unwrap();
+        let registry = registry();
+        // a new hash order every time
+        for _ in 0..20 {
+            let stats = ShopStats::new(&definitions, &registry).unwrap();
+            assert_eq!(stats.damage(), 1);
+        }
+    }
+
+    #[test]
+    fn level_past_the_last_one_keeps_the_last_effects() {
+        let mut harness = Harness::new();
+        let mut stats = harness.world_mut().resource_mut::<ShopStats>();
+        let last = stats.upgrades[&ShopItem::Burst].levels.len() as u8;
+        stats.set_level(ShopItem::Burst, last);
+        let burst = stats.burst();
+        stats.set_level(ShopItem::Burst, last + 5);
+        assert_eq!(stats.burst(), burst);
+    }
+
+    #[test]
+    fn bad_upgrade_data_fails_to_load() {
+        let registry = registry();
+        let missing_stat: UpgradeDefinitions = bevy::asset::ron::from_str(
+            "(
+                refund: 0.5,
+                upgrades: [(item: Damage, name: \"Damage\", levels: [(effects: {Damage: 1.0})])],
+            )",
+        )
+        .unwrap();
+        let error = ShopStats::new(&missing_stat, &registry).unwrap_err();
+        assert!(error.message.contains("Speed"), "{}", error);
+
+        // a typo in one cost
+        let text = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/upgrades.ron"))
+            .replacen("\"purple\"", "\"purpel\"", 1);
+        let unknown_block: UpgradeDefinitions = bevy::asset::ron::from_str(&text).unwrap();
+        let error = ShopStats::new(&unknown_block, &registry).unwrap_err();
+        assert!(error.message.contains("purpel"), "{}", error);
+    }
+
+    #[test]
+    fn upgrades_wait_for_their_prerequisites() {
+        let mut harness = Harness::new();
+        harness.spawn_shop_panel(PanelKind::Upgrade(ShopItem::Burst));
+        let paddle = harness.paddle();
+        let block_types: Vec<_> = harness.world().resource::<BlockRegistry>().iter().collect();
+        let mut resources = harness
+            .world_mut()
+            .get_mut::<CollectedResources>(paddle)
+            .unwrap();
+        for block_type in block_types {
+            resources.counts.insert(block_type, 1000);
+        }
+
+        harness.tap(KeyCode::KeyE);
+        let stats = harness.world().resource::<ShopStats>();
+        assert_eq!(stats.level(ShopItem::Burst), 1);
+        assert_eq!(stats.burst(), 1);
+
+        harness
+            .world_mut()
+            .resource_mut::<ShopStats>()
+            .set_level(ShopItem::Capacity, 2);
+        harness.tap(KeyCode::KeyE);
+        let stats = harness.world().resource::<ShopStats>();
+        assert_eq!(stats.level(ShopItem::Burst), 2);
+        assert_eq!(stats.burst(), 2);
+    }
+
+    #[test]
+    fn respec_refunds_the_ledger_and_resets_the_paddle() {
+        let mut harness = Harness::new().with_resources(&[("light_blue", 20)]);
+        let size_panel = harness.spawn_shop_panel(PanelKind::Upgrade(ShopItem::Size));
+        let paddle = harness.paddle();
+        let half_width = |harness: &Harness| {
+            harness
+                .world()
+                .get::<Collider>(paddle)
+                .and_then(|collider| collider.as_cuboid().map(|cuboid| cuboid.half_extents().x))
+                .unwrap()
+        };
+        let level_one_width = harness.world().resource::<ShopStats>().size() / 2.0;
+
+        harness.tap(KeyCode::KeyE);
+        assert_eq!(harness.collected(paddle, "light_blue"), 10);
+        assert!(half_width(&harness) > level_one_width);
+
+        // rotated by hand, which the respec takes away again
+        harness
+            .world_mut()
+            .resource_mut::<ShopStats>()
+            .set_level(ShopItem::Rotate, 2);
+        *harness.world_mut().get_mut::<TiltMode>(paddle).unwrap() = TiltMode::Manual;
+        harness
+            .world_mut()
+            .get_mut::<Transform>(paddle)
+            .unwrap()
+            .rotation = Quat::from_rotation_z(0.3);
+
+        harness.world_mut().despawn(size_panel);
+        harness.spawn_shop_panel(PanelKind::Respec);
+        harness.tap(KeyCode::KeyE);
+
+        let stats = harness.world().resource::<ShopStats>();
+        assert_eq!(stats.level(ShopItem::Size), 1);
+        assert!(stats.refund_total().is_empty());
+        // 75% of the 10 spent, rounded down
+        assert_eq!(harness.collected(paddle, "light_blue"), 17);
+        assert_eq!(half_width(&harness), level_one_width);
+        assert_eq!(
+            harness.world().get::<TiltMode>(paddle),
+            Some(&TiltMode::Auto)
+        );
+        let angle = harness.world().get::<Transform>(paddle).unwrap().rotation;
+        assert!(angle.to_euler(EulerRot::XYZ).2.abs() < 0.01, "{:?}", angle);
+    }
+
+    #[test]
+    fn try_buy_only_spends_what_it_can_afford() {
+        let harness = Harness::new();
+        let stats = harness.world().resource::<ShopStats>();
+        let cost = stats
+            .cost(ShopItem::Damage)
+            .expect("Damage can be upgraded.");
+        let purple = harness.block_type("purple");
+
+        let mut owned = cost.clone();
+        owned.insert(purple, owned[&purple] + 2);
+        assert!(try_buy(&cost, &mut owned));
+        assert_eq!(owned[&purple], 2);
+        assert!(!try_buy(&cost, &mut owned));
+        assert_eq!(owned[&purple], 2);
+    }
+}
@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_hui::prelude::{
+    FunctionBindings, HtmlFunctions, HtmlNode, TemplateProperties, TemplateScope, UiActive,
+};
+use leafwing_input_manager::prelude::{ActionState, Buttonlike, InputMap, VirtualDPad};
+
+use crate::{
+    asset_loading::{GameImageAssets, UiComponentAssets},
+    ball::CollectedResources,
+    blocks::{BlockRegistry, BlockType},
+    paddle::{Paddle, PaddleAction},
+    shop::{ShopItem, ShopState, ShopStats, on_shop_item_pressed},
+    shoppanel::{UpdateShopPanelsEvent, upgrade_icon},
+};
+
+pub struct ShopMenuPlugin;
+
+impl Plugin for ShopMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShopMenuSelection>()
+            .add_systems(Startup, register_shop_menu_functions)
+            .add_systems(OnEnter(ShopState::Open), spawn_shop_menu)
+            .add_systems(OnExit(ShopState::Open), despawn_shop_menu)
+            .add_systems(
+                Update,
+                (navigate_shop_menu, highlight_shop_items)
+                    .chain()
+                    .run_if(in_state(ShopState::Open)),
+            )
+            .add_observer(refresh_shop_menu);
+    }
+}
+
+/// How far the move stick or keys have to be pushed to step the selection.
+const NAVIGATE_THRESHOLD: f32 = 0.5;
+/// Template function that buys the upgrade of the row it's called on.
+const BUY_FUNCTION: &str = "buy_upgrade";
+const STATUS_COLOUR: Color = Color::srgb(0.6, 0.6, 0.6);
+
+/// Row of the shop menu picked with the keys or a gamepad.
+#[derive(Resource, Default, Debug)]
+pub struct ShopMenuSelection(pub usize);
+
+#[derive(Component)]
+pub struct ShopMenu;
+
+#[derive(Component)]
+struct ShopMenuList;
+
+/// Row in the shop menu, in `upgrades.ron` order.
+#[derive(Component, Debug)]
+pub struct ShopMenuItem(pub usize);
+
+/// Block shown by a cost entry in a row.
+#[derive(Component)]
+struct ShopMenuCost(BlockType);
+
+fn register_shop_menu_functions(mut functions: HtmlFunctions) {
+    functions.register(BUY_FUNCTION, on_shop_item_pressed);
+    functions.register("select_shop_item", on_shop_item_hovered);
+    functions.register("spawn_shop_rows", on_shop_list_spawned);
+    functions.register("spawn_shop_costs", on_shop_costs_spawned);
+    functions.register("show_upgrade_icon", on_upgrade_icon_spawned);
+    functions.register("show_resource_icon", on_resource_icon_spawned);
+}
+
+fn spawn_shop_menu(
+    mut commands: Commands,
+    ui: Res<UiComponentAssets>,
+    paddle_query: Query<&InputMap<PaddleAction>, With<Paddle>>,
+) {
+    let hint = paddle_query
+        .get_single()
+        .map(shop_menu_hint)
+        .unwrap_or_default();
+    commands.spawn((
+        ShopMenu,
+        HtmlNode(ui.shop.clone()),
+        TemplateProperties::default().with("hint", &hint),
+        Name::new("Shop Menu"),
+    ));
+}
+
+/// Controls line under the list, read from the paddle's bindings so it follows any remapping.
+fn shop_menu_hint(input_map: &InputMap<PaddleAction>) -> String {
+    let select = input_map
+        .get_dual_axislike(&PaddleAction::Move)
+        .into_iter()
+        .flatten()
+        .find_map(|input| {
+            let dpad = input.as_ref().as_reflect().downcast_ref::<VirtualDPad>()?;
+            Some(format!(
+                "{}/{}",
+                key_name(dpad.up.as_ref())?,
+                key_name(dpad.down.as_ref())?
+            ))
+        });
+    let first_key = |action| {
+        input_map
+            .get_buttonlike(&action)
+            .into_iter()
+            .flatten()
+            .find_map(|input| key_name(input.as_ref()))
+    };
+    [
+        (select, "select"),
+        (first_key(PaddleAction::Interact), "buy"),
+        (first_key(PaddleAction::Shop), "close"),
+    ]
+    .into_iter()
+    .filter_map(|(key, action)| Some(format!("<{}> {}", key?, action)))
+    .collect::<Vec<_>>()
+    .join("   ")
+}
+
+/// Short name of a keyboard binding, `None` for any other kind of input.
+fn key_name(input: &dyn Buttonlike) -> Option<String> {
+    let key = format!("{:?}", input.as_reflect().downcast_ref::<KeyCode>()?);
+    let name = key
+        .strip_prefix("Key")
+        .or_else(|| key.strip_prefix("Arrow"))
+        .unwrap_or(&key);
+    Some(name.to_string())
+}
+
+fn on_shop_list_spawned(
+    In(entity): In<Entity>,
+    ui: Res<UiComponentAssets>,
+    stats: Res<ShopStats>,
+    paddle_query: Query<&CollectedResources, With<Paddle>>,
+    mut commands: Commands,
+) {
+    let owned = paddle_query
+        .get_single()
+        .map(|resources| resources.counts.clone())
+        .unwrap_or_default();
+    commands
+        .entity(entity)
+        .insert(ShopMenuList)
+        .with_children(|parent| spawn_shop_rows(parent, &ui, &stats, &owned));
+}
+
+fn spawn_shop_rows(
+    parent: &mut ChildBuilder,
+    ui: &UiComponentAssets,
+    stats: &ShopStats,
+    owned: &HashMap<BlockType, u32>,
+) {
+    for (index, item) in stats.items().enumerate() {
+        let (status, status_colour) = shop_item_status(stats, item, owned);
+        parent.spawn((
+            HtmlNode(ui.shop_item.clone()),
+            TemplateProperties::default()
+                .with("name", stats.name(item))
+                .with("level", &stats.level(item).to_string())
+                .with("status", &status)
+                .with("status_color", &status_colour.to_srgba().to_hex()),
+            item,
+            ShopMenuItem(index),
+        ));
+    }
+}
+
+fn shop_item_status(
+    stats: &ShopStats,
+    item: ShopItem,
+    owned: &HashMap<BlockType, u32>,
+) -> (String, Color) {
+    if let Some(requirement) = stats.missing_requirement(item) {
+        return (
+            format!(
+                "Needs {} {}",
+                stats.name(requirement.item),
+                requirement.level
+            ),
+            STATUS_COLOUR,
+        );
+    }
+    match stats.cost(item) {
+        None => ("Maxed".to_string(), STATUS_COLOUR),
+        Some(cost)
+            if cost
+                .iter()
+                .all(|(block_type, count)| owned.get(block_type) >= Some(count)) =>
+        {
+            ("Buy".to_string(), Color::srgb(0.4, 1.0, 0.4))
+        }
+        Some(_) => ("Can't afford".to_string(), Color::srgb(1.0, 0.4, 0.4)),
+    }
+}
+
+/// Fill a row's cost line with one resource per block, in registry order.
+fn on_shop_costs_spawned(
+    In(entity): In<Entity>,
+    scope_query: Query<&TemplateScope>,
+    item_query: Query<&ShopItem>,
+    ui: Res<UiComponentAssets>,
+    stats: Res<ShopStats>,
+    registry: Res<BlockRegistry>,
+    mut commands: Commands,
+) {
+    let Some(item) = scope_query
+        .get(entity)
+        .ok()
+        .and_then(|scope| item_query.get(**scope).ok())
+    else {
+        return;
+    };
+    let mut cost: Vec<_> = stats.cost(*item).into_iter().flatten().collect();
+    cost.sort_by_key(|(block_type, _)| block_type.0);
+    commands.entity(entity).with_children(|parent| {
+        for (block_type, count) in cost {
+            parent.spawn((
+                HtmlNode(ui.resource.clone()),
+                TemplateProperties::default()
+                    .with("count", &count.to_string())
+                    .with(
+                        "color",
+                        &registry.get(block_type).colour.to_srgba().to_hex(),
+                    ),
+                ShopMenuCost(block_type),
+            ));
+        }
+    });
+}
+
+fn on_upgrade_icon_spawned(
+    In(entity): In<Entity>,
+    scope_query: Query<&TemplateScope>,
+    item_query: Query<&ShopItem>,
+    mut image_query: Query<&mut ImageNode>,
+    assets: Res<GameImageAssets>,
+) {
+    if let (Ok(scope), Ok(mut image)) = (scope_query.get(entity), image_query.get_mut(entity)) {
+        if let Ok(item) = item_query.get(**scope) {
+            image.image = upgrade_icon(&assets, *item);
+        }
+    }
+}
+
+fn on_resource_icon_spawned(
+    In(entity): In<Entity>,
+    scope_query: Query<&TemplateScope>,
+    cost_query: Query<&ShopMenuCost>,
+    mut image_query: Query<&mut ImageNode>,
+    registry: Res<BlockRegistry>,
+) {
+    if let (Ok(scope), Ok(mut image)) = (scope_query.get(entity), image_query.get_mut(entity)) {
+        if let Ok(cost) = cost_query.get(**scope) {
+            image.image = registry.get(cost.0).image.clone();
+        }
+    }
+}
+
+fn on_shop_item_hovered(
+    In(entity): In<Entity>,
+    query: Query<&ShopMenuItem>,
+    mut selection: ResMut<ShopMenuSelection>,
+) {
+    if let Ok(item) = query.get(entity) {
+        selection.0 = item.0;
+    }
+}
+
+fn despawn_shop_menu(mut commands: Commands, query: Query<Entity, With<ShopMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Rebuild the rows after a purchase so levels, costs and affordability are current.
+fn refresh_shop_menu(
+    _trigger: Trigger<UpdateShopPanelsEvent>,
+    list_query: Query<Entity, With<ShopMenuList>>,
+    ui: Res<UiComponentAssets>,
+    stats: Res<ShopStats>,
+    paddle_query: Query<&CollectedResources, With<Paddle>>,
+    mut commands: Commands,
+) {
+    let owned = paddle_query
+        .get_single()
+        .map(|resources| resources.counts.clone())
+        .unwrap_or_default();
+    for list in list_query.iter() {
+        commands
+            .entity(list)
+            .despawn_descendants()
+            .with_children(|parent| spawn_shop_rows(parent, &ui, &stats, &owned));
+    }
+}
+
+fn navigate_shop_menu(
+    query: Query<&ActionState<PaddleAction>, With<Paddle>>,
+    item_query: Query<(Entity, &ShopMenuItem)>,
+    mut selection: ResMut<ShopMenuSelection>,
+    mut previous: Local<f32>,
+    functions: Res<FunctionBindings>,
+    mut commands: Commands,
+) {
+    let Ok(action_state) = query.get_single() else {
+        return;
+    };
+    let count = item_query.iter().count();
+    if count == 0 {
+        return;
+    }
+
+    // step once per push, not every frame it's held
+    let y = action_state.clamped_axis_pair(&PaddleAction::Move).y;
+    if previous.abs() < NAVIGATE_THRESHOLD && y.abs() >= NAVIGATE_THRESHOLD {
+        selection.0 = if y > 0.0 {
+            (selection.0 + count - 1) % count
+        } else {
+            (selection.0 + 1) % count
+        };
+    }
+    *previous = y;
+    selection.0 = selection.0.min(count - 1);
+
+    if action_state.just_pressed(&PaddleAction::Interact) {
+        if let Some((entity, _)) = item_query.iter().find(|(_, item)| item.0 == selection.0) {
+            functions.maybe_run(&BUY_FUNCTION.to_string(), entity, &mut commands);
+        }
+    }
+}
+
+/// Mark the selected row active so the template's `active:` styles apply.
+fn highlight_shop_items(
+    query: Query<(Entity, &ShopMenuItem, Has<UiActive>)>,
+    selection: Res<ShopMenuSelection>,
+    mut commands: Commands,
+) {
+    for (entity, item, active) in query.iter() {
+        let selected = item.0 == selection.0;
+        if selected && !active {
+            commands.entity(entity).insert(UiActive);
+        } else if !selected && active {
+            commands.entity(entity).remove::<UiActive>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        harness::Harness,
+        shop::{ShopItem, ShopState, ShopStats},
+    };
+
+    #[test]
+    fn shop_menu_pauses_the_game_and_buys_the_selected_upgrade() {
+        let mut harness = Harness::new().with_resources(&[("purple", 10)]);
+        let paddle = harness.paddle();
+        harness.fire();
+        let ball = harness.balls()[0];
+
+        harness.tap(KeyCode::Tab);
+        assert_eq!(
+            *harness.world().resource::<State<ShopState>>().get(),
+            ShopState::Open
+        );
+        assert!(harness.world().resource::<Time<Virtual>>().is_paused());
+        let position = harness.world().get::<Transform>(ball).unwrap().translation;
+        harness.step(30);
+        assert_eq!(
+            harness.world().get::<Transform>(ball).unwrap().translation,
+            position
+        );
+        let rows = harness
+            .world_mut()
+            .query::<&ShopMenuItem>()
+            .iter(harness.world())
+            .count();
+        assert_eq!(
+            rows,
+            harness.world().resource::<ShopStats>().items().count()
+        );
+        let hint = harness
+            .world_mut()
+            .query::<&Text>()
+            .iter(harness.world())
+            .any(|text| text.0 == "<Up/Down> select   <E> buy   <Tab> close");
+        assert!(hint);
+
+        // down and back up to Damage, the first upgrade
+        harness.tap(KeyCode::KeyS);
+        assert_eq!(harness.world().resource::<ShopMenuSelection>().0, 1);
+        let active: Vec<_> = harness
+            .world_mut()
+            .query_filtered::<&ShopMenuItem, With<UiActive>>()
+            .iter(harness.world())
+            .map(|item| item.0)
+            .collect();
+        assert_eq!(active, vec![1]);
+        harness.tap(KeyCode::KeyW);
+        harness.tap(KeyCode::KeyE);
+        assert_eq!(
+            harness
+                .world()
+                .resource::<ShopStats>()
+                .level(ShopItem::Damage),
+            2
+        );
+        assert_eq!(harness.collected(paddle, "purple"), 4);
+
+        harness.tap(KeyCode::Tab);
+        assert!(!harness.world().resource::<Time<Virtual>>().is_paused());
+        let menus = harness
+            .world_mut()
+            .query::<&ShopMenu>()
+            .iter(harness.world())
+            .count();
+        assert_eq!(menus, 0);
+    }
+}
@@ -2,12 +2,11 @@ use bevy::prelude::*;
 use bevy_rapier2d::prelude::{
     ActiveCollisionTypes, ActiveEvents, Collider, CollisionGroups, Sensor,
 };
-use strum::IntoEnumIterator;
 
 use crate::{
     app_state::AppState,
     asset_loading::GameImageAssets,
-    blocks::BlockType,
+    blocks::{BlockRegistry, BlockType},
     physics::PADDLE_SHOP_GROUP,
     shop::{ShopItem, ShopStats},
 };
@@ -21,12 +20,21 @@ impl Plugin for ShopPanelPlugin {
     }
 }
 
+/// What pressing interact on a panel does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelKind {
+    Upgrade(ShopItem),
+    /// Refill the paddle's balls.
+    Refresh,
+    /// Reset every upgrade for a partial refund.
+    Respec,
+}
+
 #[derive(Component)]
 pub struct ShopPanel {
     pub enabled: bool,
-    pub item: ShopItem,
+    pub kind: PanelKind,
     pub upgrade: String,
-    pub is_refresh: bool,
 }
 
 #[derive(Component)]
@@ -35,7 +43,12 @@ pub struct ShopPanelText;
 #[derive(Component)]
 pub struct ShopResourceCost(pub BlockType);
 
-fn spawn_shop(mut commands: Commands, assets: Res<GameImageAssets>) {
+fn spawn_shop(
+    mut commands: Commands,
+    assets: Res<GameImageAssets>,
+    registry: Res<BlockRegistry>,
+    shop_stats: Res<ShopStats>,
+) {
     // Spawn text
     commands
         .spawn((
@@ -70,20 +83,75 @@ fn spawn_shop(mut commands: Commands, assets: Res<GameImageAssets>) {
                 },
                 BackgroundColor(Color::srgba(33.0 / 256.0, 33.0 / 256.0, 33.0 / 256.0, 1.0)),
             ));
-            for block_type in BlockType::iter() {
+            for block_type in registry.iter() {
                 costs.with_children(|parent| {
                     parent.spawn((
                         Text::new(format!(" 1 ")),
                         TextFont { ..default() },
-                        TextColor(block_type.colour()),
+                        TextColor(registry.get(block_type).colour),
                         ShopResourceCost(block_type),
                     ));
                 });
             }
         });
 
-    const SHOP_PANEL_WIDTH: f32 = 95.0;
+    for (item, x) in [
+        (ShopItem::Speed, -100.0),
+        (ShopItem::Damage, -200.0),
+        (ShopItem::Capacity, -300.0),
+        (ShopItem::Size, -400.0),
+        (ShopItem::Rotate, -500.0),
+        (ShopItem::Pierce, 220.0),
+        (ShopItem::Charge, 320.0),
+        (ShopItem::Burst, 420.0),
+        (ShopItem::Dash, 520.0),
+    ] {
+        spawn_shop_panel(
+            &mut commands,
+            &assets,
+            item,
+            shop_stats.name(item),
+            Vec2::new(x, 300.0),
+        );
+    }
+    spawn_refresh_panel(&mut commands, &assets, Vec2::new(100.0, 300.0));
+    spawn_respec_panel(&mut commands, &assets, Vec2::new(-400.0, 430.0));
+    spawn_shop_panel(
+        &mut commands,
+        &assets,
+        ShopItem::Balls,
+        shop_stats.name(ShopItem::Balls),
+        Vec2::new(400.0, 430.0),
+    );
+
+    commands.trigger(UpdateShopPanelsEvent);
+}
+
+const SHOP_PANEL_WIDTH: f32 = 95.0;
+
+pub fn upgrade_icon(assets: &GameImageAssets, item: ShopItem) -> Handle<Image> {
+    match item {
+        ShopItem::Damage => assets.damage_icon.clone(),
+        ShopItem::Speed => assets.speed_icon.clone(),
+        ShopItem::Capacity => assets.ball_capacity_icon.clone(),
+        ShopItem::Size => assets.paddle_size_icon.clone(),
+        ShopItem::Pierce => assets.pierce_icon.clone(),
+        ShopItem::Charge => assets.charge_icon.clone(),
+        ShopItem::Burst => assets.burst_icon.clone(),
+        ShopItem::Dash => assets.dash_icon.clone(),
+        ShopItem::Rotate => assets.rotate_icon.clone(),
+        ShopItem::Balls => assets.new_ball_icon.clone(),
+    }
+}
 
+/// Panel the paddle flies over to buy the next level of `item`.
+pub fn spawn_shop_panel(
+    commands: &mut Commands,
+    assets: &GameImageAssets,
+    item: ShopItem,
+    upgrade: &str,
+    position: Vec2,
+) -> Entity {
     commands
         .spawn((
             Sprite {
@@ -93,12 +161,11 @@ fn spawn_shop(mut commands: Commands, assets: Res<GameImageAssets>) {
             },
             ShopPanel {
                 enabled: false,
-                item: ShopItem::Speed,
-                upgrade: "Speed Upgrade".to_string(),
-                is_refresh: false,
+                kind: PanelKind::Upgrade(item),
+                upgrade: upgrade.to_string(),
             },
-            Transform::from_xyz(-100.0, 300.0, -50.0),
-            Name::new("Speed Upgrade"),
+            Transform::from_translation(position.extend(-50.0)),
+            Name::new(upgrade.to_string()),
             (
                 Collider::cuboid(SHOP_PANEL_WIDTH / 2.0, SHOP_PANEL_WIDTH / 2.0),
                 ActiveCollisionTypes::all(),
@@ -109,60 +176,37 @@ fn spawn_shop(mut commands: Commands, assets: Res<GameImageAssets>) {
         ))
         .with_child((
             Sprite {
-                image: assets.speed_icon.clone(),
+                image: upgrade_icon(assets, item),
                 custom_size: Some(Vec2::new(50.0, 50.0)),
                 ..Default::default()
             },
             Transform::from_xyz(0., -6.0, 1.0),
-        ));
-    commands
-        .spawn((
-            Sprite {
-                image: assets.shop_background.clone(),
-                custom_size: Some(Vec2::new(SHOP_PANEL_WIDTH, SHOP_PANEL_WIDTH)),
-                ..Default::default()
-            },
-            ShopPanel {
-                enabled: false,
-                item: ShopItem::Damage,
-                upgrade: "Ball Damage Upgrade".to_string(),
-                is_refresh: false,
-            },
-            Transform::from_xyz(-200.0, 300.0, -50.0),
-            Name::new("Ball Upgrade"),
-            (
-                Collider::cuboid(SHOP_PANEL_WIDTH / 2.0, SHOP_PANEL_WIDTH / 2.0),
-                ActiveCollisionTypes::all(),
-                ActiveEvents::COLLISION_EVENTS,
-                Sensor,
-                CollisionGroups::new(PADDLE_SHOP_GROUP, PADDLE_SHOP_GROUP),
-            ),
         ))
-        .with_child((
-            Sprite {
-                image: assets.damage_icon.clone(),
-                custom_size: Some(Vec2::new(50.0, 50.0)),
-                ..Default::default()
-            },
-            Transform::from_xyz(0., -6.0, 1.0),
-        ));
+        .id()
+}
+
+/// Panel that refills the paddle's balls.
+pub fn spawn_refresh_panel(
+    commands: &mut Commands,
+    assets: &GameImageAssets,
+    position: Vec2,
+) -> Entity {
     commands
         .spawn((
             Sprite {
                 image: assets.shop_background.clone(),
-                custom_size: Some(Vec2::new(SHOP_PANEL_WIDTH, SHOP_PANEL_WIDTH)),
+                custom_size: Some(Vec2::new(SHOP_PANEL_WIDTH * 1.5, SHOP_PANEL_WIDTH * 1.5)),
                 ..Default::default()
             },
             ShopPanel {
                 enabled: false,
-                item: ShopItem::Capacity,
-                upgrade: "Capacity Upgrade".to_string(),
-                is_refresh: false,
+                kind: PanelKind::Refresh,
+                upgrade: "Refresh".to_string(),
             },
-            Transform::from_xyz(-300.0, 300.0, -50.0),
-            Name::new("Capacity Upgrade"),
+            Transform::from_translation(position.extend(-50.0)),
+            Name::new("Refresh"),
             (
-                Collider::cuboid(SHOP_PANEL_WIDTH / 2.0, SHOP_PANEL_WIDTH / 2.0),
+                Collider::cuboid(SHOP_PANEL_WIDTH * 1.5 / 2.0, SHOP_PANEL_WIDTH * 1.5 / 2.0),
                 ActiveCollisionTypes::all(),
                 ActiveEvents::COLLISION_EVENTS,
                 Sensor,
@@ -171,12 +215,21 @@ fn spawn_shop(mut commands: Commands, assets: Res<GameImageAssets>) {
         ))
         .with_child((
             Sprite {
-                image: assets.ball_capacity_icon.clone(),
-                custom_size: Some(Vec2::new(50.0, 50.0)),
+                image: assets.refresh_icon.clone(),
+                custom_size: Some(Vec2::new(100.0, 100.0)),
                 ..Default::default()
             },
-            Transform::from_xyz(0., -6.0, 1.0),
-        ));
+            Transform::from_xyz(0., -15.0, 1.0),
+        ))
+        .id()
+}
+
+/// Panel that resets every upgrade for a partial refund.
+pub fn spawn_respec_panel(
+    commands: &mut Commands,
+    assets: &GameImageAssets,
+    position: Vec2,
+) -> Entity {
     commands
         .spawn((
             Sprite {
@@ -186,12 +239,11 @@ fn spawn_shop(mut commands: Commands, assets: Res<GameImageAssets>) {
             },
             ShopPanel {
                 enabled: false,
-                item: ShopItem::Size,
-                upgrade: "Size Upgrade".to_string(),
-                is_refresh: false,
+                kind: PanelKind::Respec,
+                upgrade: "Respec".to_string(),
             },
-            Transform::from_xyz(-400.0, 300.0, -50.0),
-            Name::new("Size Upgrade"),
+            Transform::from_translation(position.extend(-50.0)),
+            Name::new("Respec"),
             (
                 Collider::cuboid(SHOP_PANEL_WIDTH / 2.0, SHOP_PANEL_WIDTH / 2.0),
                 ActiveCollisionTypes::all(),
@@ -202,47 +254,13 @@ fn spawn_shop(mut commands: Commands, assets: Res<GameImageAssets>) {
         ))
         .with_child((
             Sprite {
-                image: assets.paddle_size_icon.clone(),
+                image: assets.respec_icon.clone(),
                 custom_size: Some(Vec2::new(50.0, 50.0)),
                 ..Default::default()
             },
             Transform::from_xyz(0., -6.0, 1.0),
-        ));
-
-    // refresh panel
-    commands
-        .spawn((
-            Sprite {
-                image: assets.shop_background.clone(),
-                custom_size: Some(Vec2::new(SHOP_PANEL_WIDTH * 1.5, SHOP_PANEL_WIDTH * 1.5)),
-                ..Default::default()
-            },
-            ShopPanel {
-                enabled: false,
-                item: ShopItem::Damage, // doesn't matter
-                upgrade: "Refresh".to_string(),
-                is_refresh: true,
-            },
-            Transform::from_xyz(100.0, 300.0, -50.0),
-            Name::new("Refresh"),
-            (
-                Collider::cuboid(SHOP_PANEL_WIDTH * 1.5 / 2.0, SHOP_PANEL_WIDTH * 1.5 / 2.0),
-                ActiveCollisionTypes::all(),
-                ActiveEvents::COLLISION_EVENTS,
-                Sensor,
-                CollisionGroups::new(PADDLE_SHOP_GROUP, PADDLE_SHOP_GROUP),
-            ),
         ))
-        .with_child((
-            Sprite {
-                image: assets.refresh_icon.clone(),
-                custom_size: Some(Vec2::new(100.0, 100.0)),
-                ..Default::default()
-            },
-            Transform::from_xyz(0., -15.0, 1.0),
-        ));
-
-    commands.trigger(UpdateShopPanelsEvent);
+        .id()
 }
 
 #[derive(Event, Debug, Default)]
@@ -265,29 +283,44 @@ fn update_shop_panels(
             any_enabled = true;
             sprite.color = Color::WHITE; // Normal color
 
-            if shop_panel.is_refresh {
-                shop_text.0 = format!("Press <E> to reload balls!");
-                for (mut text, block_type) in shop_cost_query.iter_mut() {
-                    text.0 = format!("");
+            let item = match shop_panel.kind {
+                PanelKind::Upgrade(item) => item,
+                PanelKind::Refresh => {
+                    shop_text.0 = format!("Press <E> to reload balls!");
+                    for (mut text, block_type) in shop_cost_query.iter_mut() {
+                        text.0 = format!("");
+                    }
+                    continue;
+                }
+                PanelKind::Respec => {
+                    // show what would come back instead of a cost
+                    shop_text.0 = format!(
+                        "Press <E> to reset all upgrades, refunds {}%",
+                        (shop_stats.refund() * 100.0).round()
+                    );
+                    let refund = shop_stats.refund_total();
+                    for (mut text, block_type) in shop_cost_query.iter_mut() {
+                        if let Some(&count) = refund.get(&block_type.0) {
+                            text.0 = format!(" +{} ", count);
+                        } else {
+                            text.0.clear();
+                        }
+                    }
+                    continue;
                 }
-                continue;
-            }
-
-            let level = match shop_panel.item {
-                ShopItem::Damage => shop_stats.damage_level,
-                ShopItem::Speed => shop_stats.speed_level,
-                ShopItem::Capacity => shop_stats.capacity_level,
-                ShopItem::Size => shop_stats.size_level,
             };
 
+            let level = shop_stats.level(item);
+
             shop_text.0 = format!("Press <E> to buy {} (level {})", shop_panel.upgrade, level);
+            if let Some(requirement) = shop_stats.missing_requirement(item) {
+                shop_text.0 = format!(
+                    "{} needs {:?} level {}",
+                    shop_panel.upgrade, requirement.item, requirement.level
+                );
+            }
             // update costs
-            if let Some(cost) = match shop_panel.item {
-                ShopItem::Damage => shop_stats.damage_cost(),
-                ShopItem::Speed => shop_stats.speed_cost(),
-                ShopItem::Capacity => shop_stats.capacity_cost(),
-                ShopItem::Size => shop_stats.size_cost(),
-            } {
+            if let Some(cost) = shop_stats.cost(item) {
                 for (mut text, block_type) in shop_cost_query.iter_mut() {
                     if let Some(&count) = cost.get(&block_type.0) {
                         text.0 = format!(" {} ", count);
@@ -311,3 +344,32 @@ fn update_shop_panels(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::Harness;
+
+    #[test]
+    fn interacting_with_shop_panel_buys_upgrade() {
+        let mut harness = Harness::new().with_resources(&[("purple", 10)]);
+        harness.spawn_shop_panel(PanelKind::Upgrade(ShopItem::Damage));
+        let paddle = harness.paddle();
+
+        let level = harness
+            .world()
+            .resource::<ShopStats>()
+            .level(ShopItem::Damage);
+        harness.tap(KeyCode::KeyE);
+
+        assert_eq!(
+            harness
+                .world()
+                .resource::<ShopStats>()
+                .level(ShopItem::Damage),
+            level + 1
+        );
+        assert_eq!(harness.collected(paddle, "purple"), 4);
+    }
+}
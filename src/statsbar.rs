@@ -1,13 +1,15 @@
 use bevy::prelude::*;
-use strum::IntoEnumIterator;
 
 use crate::{
     BackgroundHoriWall,
     app_state::AppState,
     asset_loading::GameImageAssets,
-    ball::CollectedResources,
-    blocks::{BlockType, WALL_WIDTH},
+    ball::{BallFiredEvent, BallLostEvent, BallReturnedEvent, CollectedResources},
+    blocks::{BlockRegistry, BlockType, WALL_WIDTH},
+    loadout::{BallKind, Loadout},
     paddle::Paddle,
+    seed::Seed,
+    strata::StratumEnteredEvent,
 };
 
 pub struct StatsBarPlugin;
@@ -19,16 +21,26 @@ pub struct StatsBar;
 pub struct StatsBarText(pub String);
 #[derive(Component)]
 pub struct StatsBarResource(pub BlockType);
+#[derive(Component)]
+pub struct StatsBarBallIcon;
 
 #[derive(Component)]
 pub struct StatsBarBackground;
 
 impl Plugin for StatsBarPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(AppState::Game), spawn_stats_bar)
+        app.init_resource::<BallTally>()
+            .add_systems(OnEnter(AppState::Game), spawn_stats_bar)
             .add_observer(update_stats_bar_resources)
             .add_observer(update_stats_bar_depth)
-            .add_observer(update_stats_bar_balls);
+            .add_observer(update_stats_bar_balls)
+            .add_observer(update_stats_bar_ball_kind)
+            .add_observer(update_stats_bar_magnet)
+            .add_observer(update_stats_bar_dash)
+            .add_observer(update_stats_bar_stratum)
+            .add_observer(count_fired_ball)
+            .add_observer(count_lost_ball)
+            .add_observer(update_stats_bar_returned_ball);
     }
 }
 
@@ -38,7 +50,13 @@ fn spawn_stats_bar(
     mut commands: Commands,
     assets: Res<GameImageAssets>,
     camera_query: Query<(Entity, &Camera, &GlobalTransform, &OrthographicProjection)>,
+    registry: Res<BlockRegistry>,
+    seed: Res<Seed>,
+    loadout: Res<Loadout>,
+    mut tally: ResMut<BallTally>,
 ) {
+    *tally = BallTally::default();
+
     // add ui
     commands
         .spawn((
@@ -84,6 +102,14 @@ fn spawn_stats_bar(
                             StatsBarText("Depth".to_string()),
                         ));
                     });
+                    parent.spawn((text_bg.clone(),)).with_children(|parent| {
+                        parent.spawn((
+                            Text::new(registry.strata()[0].name.clone()),
+                            TextFont { ..default() },
+                            TextColor(Color::WHITE),
+                            StatsBarText("Stratum".to_string()),
+                        ));
+                    });
                     parent.spawn((text_bg.clone(),)).with_children(|parent| {
                         parent.spawn((
                             Text::new("Balls: 3"),
@@ -92,6 +118,74 @@ fn spawn_stats_bar(
                             StatsBarText("Balls".to_string()),
                         ));
                     });
+                    parent.spawn((text_bg.clone(),)).with_children(|parent| {
+                        parent.spawn((
+                            Text::new(tally.text()),
+                            TextFont { ..default() },
+                            TextColor(Color::WHITE),
+                            StatsBarText("BallTally".to_string()),
+                        ));
+                    });
+                    parent.spawn((text_bg.clone(),)).with_children(|parent| {
+                        parent.spawn((
+                            ImageNode::new(ball_icon(&assets, loadout.selected())),
+                            Node {
+                                width: Val::Px(20.0),
+                                height: Val::Px(20.0),
+                                margin: UiRect::right(Val::Px(4.0)),
+                                ..default()
+                            },
+                            StatsBarBallIcon,
+                        ));
+                        parent.spawn((
+                            Text::new(loadout.selected().name()),
+                            TextFont { ..default() },
+                            TextColor(Color::WHITE),
+                            StatsBarText("BallKind".to_string()),
+                        ));
+                    });
+                    parent.spawn((text_bg.clone(),)).with_children(|parent| {
+                        parent.spawn((
+                            ImageNode::new(assets.magnet_icon.clone()),
+                            Node {
+                                width: Val::Px(20.0),
+                                height: Val::Px(20.0),
+                                margin: UiRect::right(Val::Px(4.0)),
+                                ..default()
+                            },
+                        ));
+                        parent.spawn((
+                            Text::new(magnet_text(1.0)),
+                            TextFont { ..default() },
+                            TextColor(Color::WHITE),
+                            StatsBarText("Magnet".to_string()),
+                        ));
+                    });
+                    parent.spawn((text_bg.clone(),)).with_children(|parent| {
+                        parent.spawn((
+                            ImageNode::new(assets.dash_icon.clone()),
+                            Node {
+                                width: Val::Px(20.0),
+                                height: Val::Px(20.0),
+                                margin: UiRect::right(Val::Px(4.0)),
+                                ..default()
+                            },
+                        ));
+                        parent.spawn((
+                            Text::new(dash_text(0.0)),
+                            TextFont { ..default() },
+                            TextColor(Color::WHITE),
+                            StatsBarText("Dash".to_string()),
+                        ));
+                    });
+                    parent.spawn((text_bg.clone(),)).with_children(|parent| {
+                        parent.spawn((
+                            Text::new(format!("Seed: {}", seed.run_code())),
+                            TextFont { ..default() },
+                            TextColor(Color::srgb(0.6, 0.6, 0.6)),
+                            StatsBarText("Seed".to_string()),
+                        ));
+                    });
                 });
 
             // right stuff is for CollectedResources
@@ -104,12 +198,12 @@ fn spawn_stats_bar(
                 },
                 BackgroundColor(Color::srgba(33.0 / 256.0, 33.0 / 256.0, 33.0 / 256.0, 1.0)),
             ));
-            for block_type in BlockType::iter() {
+            for block_type in registry.iter() {
                 right.with_children(|parent| {
                     parent.spawn((
                         Text::new(format!("")),
                         TextFont { ..default() },
-                        TextColor(block_type.colour()),
+                        TextColor(registry.get(block_type).colour),
                         StatsBarResource(block_type),
                     ));
                 });
@@ -175,3 +269,139 @@ fn update_stats_bar_balls(
         }
     }
 }
+
+fn update_stats_bar_stratum(
+    trigger: Trigger<StratumEnteredEvent>,
+    mut query: Query<(&StatsBarText, &mut Text)>,
+    registry: Res<BlockRegistry>,
+) {
+    for (stats_bar_text, mut text) in query.iter_mut() {
+        if stats_bar_text.0 == "Stratum" {
+            text.0 = registry.strata()[trigger.stratum].name.clone();
+        }
+    }
+}
+
+fn ball_icon(assets: &GameImageAssets, kind: BallKind) -> Handle<Image> {
+    assets
+        .ball_icons
+        .get(kind.icon())
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[derive(Event, Debug)]
+pub struct UpdateStatsBarBallKindEvent {
+    pub kind: BallKind,
+}
+
+fn update_stats_bar_ball_kind(
+    trigger: Trigger<UpdateStatsBarBallKindEvent>,
+    mut query: Query<(&StatsBarText, &mut Text)>,
+    mut icon_query: Query<&mut ImageNode, With<StatsBarBallIcon>>,
+    assets: Res<GameImageAssets>,
+) {
+    for (stats_bar_text, mut text) in query.iter_mut() {
+        if stats_bar_text.0 == "BallKind" {
+            text.0 = trigger.kind.name().to_string();
+        }
+    }
+    for mut icon in icon_query.iter_mut() {
+        icon.image = ball_icon(&assets, trigger.kind);
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct UpdateStatsBarMagnetEvent {
+    pub energy: f32,
+    /// False while the magnet is cooling down after running dry.
+    pub ready: bool,
+}
+
+fn magnet_text(energy: f32) -> String {
+    format!("{:.0}%", energy * 100.0)
+}
+
+fn update_stats_bar_magnet(
+    trigger: Trigger<UpdateStatsBarMagnetEvent>,
+    mut query: Query<(&StatsBarText, &mut Text, &mut TextColor)>,
+) {
+    for (stats_bar_text, mut text, mut colour) in query.iter_mut() {
+        if stats_bar_text.0 == "Magnet" {
+            text.0 = magnet_text(trigger.energy);
+            colour.0 = if trigger.ready {
+                Color::WHITE
+            } else {
+                Color::srgb(0.6, 0.6, 0.6)
+            };
+        }
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct UpdateStatsBarDashEvent {
+    pub cooldown: f32,
+}
+
+fn dash_text(cooldown: f32) -> String {
+    if cooldown > 0.0 {
+        format!("{:.1}s", cooldown)
+    } else {
+        "Ready".to_string()
+    }
+}
+
+fn update_stats_bar_dash(
+    trigger: Trigger<UpdateStatsBarDashEvent>,
+    mut query: Query<(&StatsBarText, &mut Text)>,
+) {
+    for (stats_bar_text, mut text) in query.iter_mut() {
+        if stats_bar_text.0 == "Dash" {
+            text.0 = dash_text(trigger.cooldown);
+        }
+    }
+}
+
+/// Balls in flight and lost this run.
+#[derive(Resource, Default)]
+struct BallTally {
+    out: u32,
+    lost: u32,
+}
+
+impl BallTally {
+    fn text(&self) -> String {
+        format!("Out: {}  Lost: {}", self.out, self.lost)
+    }
+}
+
+fn update_stats_bar_tally(tally: &BallTally, query: &mut Query<(&StatsBarText, &mut Text)>) {
+    for (stats_bar_text, mut text) in query.iter_mut() {
+        if stats_bar_text.0 == "BallTally" {
+            text.0 = tally.text();
+        }
+    }
+}
+
+fn count_fired_ball(
+    _trigger: Trigger<BallFiredEvent>,
+    mut tally: ResMut<BallTally>,
+    mut query: Query<(&StatsBarText, &mut Text)>,
+) {
+    tally.out += 1;
+    update_stats_bar_tally(&tally, &mut query);
+}
+
+fn count_lost_ball(
+    _trigger: Trigger<BallLostEvent>,
+    mut tally: ResMut<BallTally>,
+    mut query: Query<(&StatsBarText, &mut Text)>,
+) {
+    tally.out = tally.out.saturating_sub(1);
+    tally.lost += 1;
+    update_stats_bar_tally(&tally, &mut query);
+}
+
+fn update_stats_bar_returned_ball(_trigger: Trigger<BallReturnedEvent>, mut commands: Commands) {
+    commands.trigger(UpdateStatsBarResourcesEvent);
+}
@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    blocks::{BLOCKS_FILE, BlockRegistry, BlockType, DefinitionError, row_at},
+    paddle::Paddle,
+};
+
+pub struct StrataPlugin;
+
+impl Plugin for StrataPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentStratum>()
+            .add_systems(OnEnter(AppState::Game), reset_current_stratum)
+            .add_systems(Update, check_stratum.run_if(in_state(AppState::Game)));
+    }
+}
+
+/// A stratum as authored in `blocks.ron`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct StratumDefinition {
+    pub id: String,
+    pub name: String,
+    /// Strata are picked top down, a stratum without a max fills everything below.
+    #[serde(default)]
+    pub max_depth: Option<f32>,
+    /// Block id used when no vein matches.
+    pub base: String,
+    /// Block ids whose veins can show up here, all veins if unset.
+    #[serde(default)]
+    pub veins: Option<Vec<String>>,
+    /// File stem of an image in the `backgrounds` dynamic asset.
+    pub background: String,
+}
+
+#[derive(Debug)]
+pub struct StratumInfo {
+    pub id: String,
+    pub name: String,
+    pub max_depth: Option<f32>,
+    pub base: BlockType,
+    pub veins: Option<Vec<BlockType>>,
+    pub background: String,
+}
+
+impl StratumInfo {
+    /// Resolve block ids against the registry.
+    pub fn new(
+        definition: &StratumDefinition,
+        registry: &BlockRegistry,
+    ) -> Result<Self, DefinitionError> {
+        let resolve = |id: &str| {
+            registry.find(id).ok_or_else(|| {
+                DefinitionError::new(
+                    BLOCKS_FILE,
+                    format!("unknown block id '{}' in stratum '{}'", id, definition.id),
+                )
+            })
+        };
+        let base = resolve(&definition.base)?;
+        let veins = definition
+            .veins
+            .as_ref()
+            .map(|ids| ids.iter().map(|id| resolve(id)).collect())
+            .transpose()?;
+        Ok(Self {
+            id: definition.id.clone(),
+            name: definition.name.clone(),
+            max_depth: definition.max_depth,
+            base,
+            veins,
+            background: definition.background.clone(),
+        })
+    }
+
+    pub fn allows_vein(&self, block_type: BlockType) -> bool {
+        self.veins
+            .as_ref()
+            .is_none_or(|veins| veins.contains(&block_type))
+    }
+}
+
+/// Index into [`BlockRegistry::strata`] of the stratum the paddle is in.
+#[derive(Resource, Default)]
+pub struct CurrentStratum(pub Option<usize>);
+
+/// Fired when the paddle moves into a different stratum, and once when the game starts.
+#[derive(Event, Debug)]
+pub struct StratumEnteredEvent {
+    pub stratum: usize,
+}
+
+fn reset_current_stratum(mut current: ResMut<CurrentStratum>) {
+    current.0 = None;
+}
+
+fn check_stratum(
+    paddle_query: Query<&Transform, With<Paddle>>,
+    registry: Res<BlockRegistry>,
+    mut current: ResMut<CurrentStratum>,
+    mut commands: Commands,
+) {
+    let Ok(transform) = paddle_query.get_single() else {
+        return;
+    };
+    let stratum = registry.stratum_at(row_at(transform.translation.y) as f32);
+    if current.0 == Some(stratum) {
+        return;
+    }
+    current.0 = Some(stratum);
+    let entered = &registry.strata()[stratum];
+    info!("Entered stratum {} ({})", entered.name, entered.id);
+    commands.trigger(StratumEnteredEvent { stratum });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::{block_definitions, block_textures};
+
+    #[test]
+    fn unknown_stratum_block_fails_to_load() {
+        let mut definitions = block_definitions();
+        definitions.strata[0].veins = Some(vec!["purpel".to_string()]);
+        let error =
+            BlockRegistry::new(&definitions, &[], &block_textures(&definitions)).unwrap_err();
+        assert!(error.message.contains("purpel"), "{}", error);
+    }
+}
@@ -0,0 +1,363 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin, RidgedMulti};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::IndexedRandom};
+
+use crate::{
+    blocks::{
+        BLOCK_COUNT_WIDTH, BlockRegistry, BlockType, DepthBand, NoiseKind, NoiseLayer,
+        OutpostPlacement, RoomPlacement,
+    },
+    rooms::{RoomInfo, RoomTile},
+    seed::Seed,
+};
+
+/// A noise function built once from a [`NoiseLayer`] and a seed.
+#[derive(Clone)]
+enum NoiseFunction {
+    Fbm(Fbm<Perlin>),
+    RidgedMulti(RidgedMulti<Perlin>),
+}
+
+impl NoiseFunction {
+    fn new(layer: &NoiseLayer, seed: u32) -> Self {
+        let seed = seed.wrapping_add(layer.seed_offset);
+        match layer.kind {
+            NoiseKind::Fbm => {
+                let mut noise = Fbm::<Perlin>::new(seed).set_frequency(layer.frequency);
+                if let Some(lacunarity) = layer.lacunarity {
+                    noise = noise.set_lacunarity(lacunarity);
+                }
+                if let Some(octaves) = layer.octaves {
+                    noise = noise.set_octaves(octaves);
+                }
+                NoiseFunction::Fbm(noise)
+            }
+            NoiseKind::RidgedMulti => {
+                let mut noise = RidgedMulti::<Perlin>::new(seed).set_frequency(layer.frequency);
+                if let Some(lacunarity) = layer.lacunarity {
+                    noise = noise.set_lacunarity(lacunarity);
+                }
+                if let Some(octaves) = layer.octaves {
+                    noise = noise.set_octaves(octaves);
+                }
+                NoiseFunction::RidgedMulti(noise)
+            }
+        }
+    }
+
+    fn get(&self, position: Vec2) -> f64 {
+        let point = [position.x as f64, position.y as f64];
+        match self {
+            NoiseFunction::Fbm(noise) => noise.get(point),
+            NoiseFunction::RidgedMulti(noise) => noise.get(point),
+        }
+    }
+}
+
+struct Vein {
+    block_type: BlockType,
+    depth: DepthBand,
+    threshold: f64,
+    noise: NoiseFunction,
+}
+
+struct Stratum {
+    max_depth: Option<f32>,
+    base: BlockType,
+    /// Indices into `veins` that can show up in this stratum.
+    veins: Vec<usize>,
+}
+
+struct Caves {
+    depth: DepthBand,
+    threshold: f64,
+    noise: NoiseFunction,
+}
+
+struct WorldGeneratorInner {
+    seed: u32,
+    blend: NoiseFunction,
+    blend_amplitude: f32,
+    caves: Option<Caves>,
+    veins: Vec<Vein>,
+    strata: Vec<Stratum>,
+    rooms: Vec<RoomInfo>,
+    room_placement: Option<RoomPlacement>,
+    /// `(top row, left column)` of every outpost, placed once from the seed.
+    outposts: Vec<(usize, usize)>,
+    outpost_size: Option<(usize, usize)>,
+}
+
+/// Picks block types for the world. The noise functions are built once from the [`Seed`] and
+/// shared, so the generator is cheap to clone into async row generation tasks.
+#[derive(Resource, Clone)]
+pub struct WorldGenerator(Arc<WorldGeneratorInner>);
+
+impl WorldGenerator {
+    pub fn new(registry: &BlockRegistry, seed: Seed) -> Self {
+        let (blend, blend_amplitude) = registry.blend();
+        let veins: Vec<Vein> = registry
+            .veins()
+            .iter()
+            .map(|(block_type, vein)| Vein {
+                block_type: *block_type,
+                depth: vein.depth,
+                threshold: vein.threshold,
+                noise: NoiseFunction::new(&vein.noise, seed.0),
+            })
+            .collect();
+        let strata = registry
+            .strata()
+            .iter()
+            .map(|stratum| Stratum {
+                max_depth: stratum.max_depth,
+                base: stratum.base,
+                veins: veins
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, vein)| stratum.allows_vein(vein.block_type))
+                    .map(|(i, _)| i)
+                    .collect(),
+            })
+            .collect();
+        Self(Arc::new(WorldGeneratorInner {
+            seed: seed.0,
+            blend: NoiseFunction::new(blend, seed.0),
+            blend_amplitude,
+            caves: registry.caves().map(|caves| Caves {
+                depth: caves.depth,
+                threshold: caves.threshold,
+                noise: NoiseFunction::new(&caves.noise, seed.0),
+            }),
+            veins,
+            strata,
+            rooms: registry.rooms().to_vec(),
+            room_placement: registry.room_placement(),
+            outposts: registry
+                .outposts()
+                .map_or_else(Vec::new, |outposts| place_outposts(outposts, seed)),
+            outpost_size: registry
+                .outposts()
+                .map(|outposts| (outposts.width.min(BLOCK_COUNT_WIDTH), outposts.height)),
+        }))
+    }
+
+    /// Block for a cell of the terrain, `None` for cave cells. Doesn't include rooms.
+    pub fn pick_block_type(&self, position: Vec2) -> Option<BlockType> {
+        let inner = &self.0;
+        let depth = inner.blend_amplitude * inner.blend.get(position) as f32 + position.y;
+        let in_cave = inner.caves.as_ref().is_some_and(|caves| {
+            caves.depth.contains(depth) && caves.noise.get(position) > caves.threshold
+        });
+        if in_cave {
+            return None;
+        }
+        let stratum = self.stratum_at(depth);
+        for vein in stratum.veins.iter().map(|&i| &inner.veins[i]) {
+            if vein.depth.contains(depth) && vein.noise.get(position) > vein.threshold {
+                return Some(vein.block_type);
+            }
+        }
+        Some(stratum.base)
+    }
+
+    fn stratum_at(&self, depth: f32) -> &Stratum {
+        let strata = &self.0.strata;
+        strata
+            .iter()
+            .find(|stratum| stratum.max_depth.is_none_or(|max| depth < max))
+            .or(strata.last())
+            .expect("Need at least one stratum.")
+    }
+
+    /// The room stamped at the `index`th placement as `(top row, left column, room)`, picked
+    /// from the seed so the same world always gets the same rooms.
+    fn room_at(&self, index: usize) -> Option<(usize, usize, &RoomInfo)> {
+        let placement = self.0.room_placement?;
+        let top = placement.first_row + index * placement.interval;
+        let candidates: Vec<&RoomInfo> = self
+            .0
+            .rooms
+            .iter()
+            .filter(|room| room.min_depth <= top as f32 && room.width() <= BLOCK_COUNT_WIDTH)
+            .collect();
+        let mut rng = StdRng::seed_from_u64(((self.0.seed as u64) << 32) | index as u64);
+        let room = candidates.choose(&mut rng)?;
+        let left = rng.random_range(0..=BLOCK_COUNT_WIDTH - room.width());
+        Some((top, left, room))
+    }
+
+    fn rooms_overlapping(&self, row: usize) -> Vec<(usize, usize, &RoomInfo)> {
+        let Some(placement) = self.0.room_placement else {
+            return Vec::new();
+        };
+        if row < placement.first_row || placement.interval == 0 {
+            return Vec::new();
+        }
+        let tallest = self.0.rooms.iter().map(RoomInfo::height).max().unwrap_or(0);
+        let offset = row - placement.first_row;
+        let first = offset.saturating_sub(tallest) / placement.interval;
+        let last = offset / placement.interval;
+        (first..=last)
+            .filter_map(|index| self.room_at(index))
+            .filter(|(top, _, room)| row >= *top && row < top + room.height())
+            .collect()
+    }
+
+    /// Outposts as `(top row, left column)`, the column picked from the seed.
+    pub fn outposts(&self) -> &[(usize, usize)] {
+        &self.0.outposts
+    }
+
+    /// Size of every outpost in cells as `(width, height)`.
+    pub fn outpost_size(&self) -> Option<(usize, usize)> {
+        self.0.outpost_size
+    }
+
+    pub fn generate_row(&self, row: usize) -> Vec<Option<BlockType>> {
+        let mut cells: Vec<Option<BlockType>> = (0..BLOCK_COUNT_WIDTH)
+            .map(|column| {
+                self.pick_block_type(Vec2 {
+                    x: column as f32,
+                    y: row as f32,
+                })
+            })
+            .collect();
+        for (top, left, room) in self.rooms_overlapping(row) {
+            for column in 0..room.width() {
+                if let RoomTile::Set(cell) = room.tile(column, row - top) {
+                    cells[left + column] = cell;
+                }
+            }
+        }
+        // outposts are carved last so rooms can't wall them in
+        if let Some((width, height)) = self.outpost_size() {
+            for &(top, left) in self.outposts() {
+                if row >= top && row < top + height {
+                    cells[left..left + width].fill(None);
+                }
+            }
+        }
+        cells
+    }
+}
+
+fn place_outposts(outposts: &OutpostPlacement, seed: Seed) -> Vec<(usize, usize)> {
+    let width = outposts.width.min(BLOCK_COUNT_WIDTH);
+    outposts
+        .rows
+        .iter()
+        .map(|&top| {
+            let mut rng = StdRng::seed_from_u64(((seed.0 as u64) << 32) | top as u64);
+            (top, rng.random_range(0..=BLOCK_COUNT_WIDTH - width))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::harness::{block_definitions, registry, registry_from};
+
+    const BENCH_ROWS: usize = 1000;
+
+    #[test]
+    fn first_upgrade_resources_show_up_in_the_shallows() {
+        let registry = registry();
+        let purple = registry.find("purple").unwrap();
+        let generator = WorldGenerator::new(&registry, Seed(1234));
+        assert!(
+            (0..50)
+                .flat_map(|row| generator.generate_row(row))
+                .any(|cell| cell == Some(purple))
+        );
+    }
+
+    #[test]
+    fn outposts_are_carved_out_at_their_rows() {
+        let registry = registry();
+        let generator = WorldGenerator::new(&registry, Seed(1234));
+        let (width, height) = generator.outpost_size().expect("blocks.ron has outposts");
+        let outposts = generator.outposts();
+        assert!(!outposts.is_empty());
+        for &(top, left) in outposts {
+            for row in top..top + height {
+                let cells = generator.generate_row(row);
+                assert!(cells[left..left + width].iter().all(Option::is_none));
+            }
+        }
+    }
+
+    /// Rebuilds the noise functions for every cell, the way generation used to work.
+    fn pick_uncached(
+        generator: &WorldGenerator,
+        registry: &BlockRegistry,
+        position: Vec2,
+    ) -> Option<BlockType> {
+        let seed = generator.0.seed;
+        let (blend, blend_amplitude) = registry.blend();
+        let depth =
+            blend_amplitude * NoiseFunction::new(blend, seed).get(position) as f32 + position.y;
+        let in_cave = registry.caves().is_some_and(|caves| {
+            caves.depth.contains(depth)
+                && NoiseFunction::new(&caves.noise, seed).get(position) > caves.threshold
+        });
+        if in_cave {
+            return None;
+        }
+        let stratum = generator.stratum_at(depth);
+        for (block_type, vein) in stratum.veins.iter().map(|&i| &registry.veins()[i]) {
+            if vein.depth.contains(depth)
+                && NoiseFunction::new(&vein.noise, seed).get(position) > vein.threshold
+            {
+                return Some(*block_type);
+            }
+        }
+        Some(stratum.base)
+    }
+
+    /// Run with `cargo test --release -- --ignored bench_first_rows`.
+    #[test]
+    #[ignore]
+    fn bench_first_rows() {
+        // picking a cell on its own knows nothing of the outposts carved out of the rows
+        let mut definitions = block_definitions();
+        definitions.outposts = None;
+        let registry = registry_from(&definitions);
+        let seed = Seed(1234);
+
+        let generator = WorldGenerator::new(&registry, seed);
+
+        let start = Instant::now();
+        let mut uncached = Vec::new();
+        for row in 0..BENCH_ROWS {
+            for column in 0..BLOCK_COUNT_WIDTH {
+                uncached.push(pick_uncached(
+                    &generator,
+                    &registry,
+                    Vec2::new(column as f32, row as f32),
+                ));
+            }
+        }
+        let uncached_time = start.elapsed();
+
+        let start = Instant::now();
+        let cached: Vec<Option<BlockType>> = (0..BENCH_ROWS)
+            .flat_map(|row| generator.generate_row(row))
+            .collect();
+        let cached_time = start.elapsed();
+
+        assert!(
+            cached_time < uncached_time,
+            "{} rows: uncached {:?}, cached {:?}",
+            BENCH_ROWS,
+            uncached_time,
+            cached_time
+        );
+        assert_eq!(uncached, cached);
+    }
+}